@@ -21,7 +21,7 @@ fn main() {
     App::new()
         .add_plugins((
                 DefaultPlugins.set(ImagePlugin::default_nearest()),
-                BevyHydaPlugin,
+                BevyHydaPlugin::default(),
             ))
         .add_systems(Startup, setup)
         .run();
@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use bevy::reflect::ReflectRef;
+use std::collections::HashMap;
+
+// One element of a bound list. The key identifies it between updates, so <template data-for>
+// only spawns (or despawns) the items that were added (or removed).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HydaBindingItem {
+    pub key: String,
+    pub values: HashMap<String, String>,
+}
+
+impl HydaBindingItem {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with<T: ToString>(mut self, field: &str, value: T) -> Self {
+        self.values.insert(field.to_string(), value.to_string());
+        self
+    }
+
+    // For lists of plain values, shown with "{{item}}".
+    pub fn with_value<T: ToString>(self, value: T) -> Self {
+        self.with("", value)
+    }
+}
+
+// Values that HTML text can show with "{{name}}" placeholders (or "data-bind-text" on an element).
+// Bound text gets updated every time this resource changes, without spawning the document again.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct HydaBindings {
+    values: HashMap<String, String>,
+    lists: HashMap<String, Vec<HydaBindingItem>>,
+}
+
+// How the fields of list items are named in the values, "list[key].field" (or "list[key]" for plain values).
+pub(crate) fn get_item_path(list: &str, key: &str, field: &str) -> String {
+    if field.is_empty() {
+        return format!("{}[{}]", list, key);
+    }
+
+    return format!("{}[{}].{}", list, key, field);
+}
+
+impl HydaBindings {
+    pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        return self.values.get(key).map(|v| v.as_str());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn values(&self) -> &HashMap<String, String> {
+        return &self.values;
+    }
+
+    // The fields of every item are also set as values, so their text updates without respawning them.
+    pub fn set_list(&mut self, name: &str, items: Vec<HydaBindingItem>) {
+        let prefix = format!("{}[", name);
+
+        self.values.retain(|k, _| !k.starts_with(&prefix));
+
+        for item in &items {
+            for (field, value) in &item.values {
+                self.values.insert(get_item_path(name, &item.key, field), value.clone());
+            }
+        }
+
+        self.lists.insert(name.to_string(), items);
+    }
+
+    pub fn list(&self, name: &str) -> Option<&Vec<HydaBindingItem>> {
+        return self.lists.get(name);
+    }
+
+    pub fn remove_list(&mut self, name: &str) {
+        self.set_list(name, Vec::new());
+        self.lists.remove(name);
+    }
+}
+
+// On text nodes that have placeholders: the original text of each one of those sections.
+#[derive(Component, Debug, Clone)]
+pub struct HydaTextBinding {
+    templates: Vec<(usize, String)>,
+}
+
+fn has_placeholders(text: &str) -> bool {
+    return text.find("{{").map(|i| text[i..].contains("}}")).unwrap_or(false);
+}
+
+// Replaces every "{{ key }}" in "template", unknown keys end up empty.
+pub fn render_template(template: &str, bindings: &HydaBindings) -> String {
+
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break; };
+
+        result.push_str(&rest[..start]);
+
+        let key = rest[start + 2..start + end].trim();
+        result.push_str(bindings.get(key).unwrap_or(""));
+
+        rest = &rest[start + end + 2..];
+    }
+
+    result.push_str(rest);
+
+    return result;
+}
+
+// Called when spawning text nodes, the placeholders are hidden until the bindings fill them.
+pub(crate) fn get_text_binding(sections: &mut Vec<TextSection>) -> Option<HydaTextBinding> {
+
+    let mut templates: Vec<(usize, String)> = Vec::new();
+
+    for (i, s) in sections.iter_mut().enumerate() {
+        if has_placeholders(&s.value) {
+            templates.push((i, s.value.clone()));
+            s.value = render_template(&s.value, &HydaBindings::default());
+        }
+    }
+
+    return if templates.is_empty() { None } else { Some(HydaTextBinding { templates: templates }) };
+}
+
+// Items use their "id" field as the key if they have one, their index otherwise.
+fn get_reflect_item(value: &dyn Reflect, index: usize) -> HydaBindingItem {
+
+    let mut values: Vec<(String, String)> = Vec::new();
+    let mut lists: Vec<(String, Vec<HydaBindingItem>)> = Vec::new();
+
+    collect_reflect_values("", value, &mut values, &mut lists);
+
+    let mut item = HydaBindingItem::new(&index.to_string());
+
+    for (k, v) in values {
+        item.values.insert(k.trim_start_matches('.').to_string(), v);
+    }
+
+    if let Some(id) = item.values.get("id") {
+        item.key = id.clone();
+    }
+
+    return item;
+}
+
+// All the fields of a reflected value, as "prefix.field.subfield" keys (lists and arrays go to "lists").
+fn collect_reflect_values(prefix: &str, value: &dyn Reflect, result: &mut Vec<(String, String)>, lists: &mut Vec<(String, Vec<HydaBindingItem>)>) {
+    match value.reflect_ref() {
+        ReflectRef::Struct(s) => {
+            for i in 0..s.field_len() {
+                let name = s.name_at(i).unwrap();
+                collect_reflect_values(&format!("{}.{}", prefix, name), s.field_at(i).unwrap(), result, lists);
+            }
+        },
+        ReflectRef::TupleStruct(t) => {
+            for i in 0..t.field_len() {
+                collect_reflect_values(&format!("{}.{}", prefix, i), t.field(i).unwrap(), result, lists);
+            }
+        },
+        ReflectRef::List(l) => {
+            lists.push((prefix.to_string(), l.iter().enumerate().map(|(i, v)| get_reflect_item(v, i)).collect()));
+        },
+        ReflectRef::Array(a) => {
+            lists.push((prefix.to_string(), a.iter().enumerate().map(|(i, v)| get_reflect_item(v, i)).collect()));
+        },
+        _ => {
+            // Strings would get quotes with Debug.
+            let text = match value.downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => format!("{:?}", value),
+            };
+
+            result.push((prefix.to_string(), text));
+        },
+    }
+}
+
+fn sync_reflected_resource<R: Resource + Reflect>(name: &str, resource: &R, bindings: &mut ResMut<HydaBindings>) {
+
+    let mut values: Vec<(String, String)> = Vec::new();
+    let mut lists: Vec<(String, Vec<HydaBindingItem>)> = Vec::new();
+
+    collect_reflect_values(name, resource.as_reflect(), &mut values, &mut lists);
+
+    // Only the ones that are different, so the bound text isn't updated for nothing.
+    for (k, v) in values {
+        if bindings.get(&k) != Some(v.as_str()) {
+            bindings.set(&k, v);
+        }
+    }
+
+    for (k, items) in lists {
+        if bindings.list(&k) != Some(&items) {
+            bindings.set_list(&k, items);
+        }
+    }
+}
+
+pub trait HydaBindingsAppExt {
+    // Keeps the fields of a resource in HydaBindings, as "name.field" (so "{{player.gold}}" for a "player" resource).
+    // Vec and array fields become lists, for <template data-for="item in name.field">.
+    fn bind_resource<R: Resource + Reflect>(&mut self, name: &str) -> &mut Self;
+}
+
+impl HydaBindingsAppExt for App {
+    fn bind_resource<R: Resource + Reflect>(&mut self, name: &str) -> &mut Self {
+        let final_name = name.to_string();
+
+        self.add_systems(Update, move |resource: Option<Res<R>>, mut bindings: ResMut<HydaBindings>| {
+            if let Some(r) = resource.filter(|r| r.is_changed()) {
+                sync_reflected_resource(&final_name, r.into_inner(), &mut bindings);
+            }
+        });
+
+        return self;
+    }
+}
+
+pub(crate) fn update_bound_text(
+    bindings: Res<HydaBindings>,
+    mut query_text: Query<(Ref<HydaTextBinding>, &mut Text)>,
+) {
+    for (binding, mut text) in &mut query_text {
+        if !bindings.is_changed() && !binding.is_added() {
+            continue;
+        }
+
+        for (i, template) in &binding.templates {
+            let value = render_template(template, &bindings);
+
+            if text.sections.get(*i).map(|s| s.value != value).unwrap_or(false) {
+                text.sections[*i].value = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_values() {
+        let mut bindings = HydaBindings::default();
+        bindings.set("player.gold", 120);
+        bindings.set("player.name", "Ayla");
+
+        assert_eq!(render_template("{{player.name}} has {{ player.gold }} gold", &bindings), "Ayla has 120 gold");
+    }
+
+    #[test]
+    fn render_unknown_and_unclosed_placeholders() {
+        let bindings = HydaBindings::default();
+
+        assert_eq!(render_template("[{{missing}}]", &bindings), "[]");
+        assert_eq!(render_template("{{missing", &bindings), "{{missing");
+        assert_eq!(render_template("no placeholders", &bindings), "no placeholders");
+    }
+
+    #[test]
+    fn render_list_items() {
+        let mut bindings = HydaBindings::default();
+        bindings.set_list("inventory", vec![
+            HydaBindingItem::new("sword").with("count", 1),
+            HydaBindingItem::new("arrow").with_value("arrows"),
+        ]);
+
+        assert_eq!(render_template("{{inventory[sword].count}} {{inventory[arrow]}}", &bindings), "1 arrows");
+
+        // Setting the list again drops the values of the items that aren't there anymore.
+        bindings.set_list("inventory", vec![HydaBindingItem::new("arrow").with_value("arrows")]);
+
+        assert_eq!(render_template("{{inventory[sword].count}}", &bindings), "");
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Event, Debug, Clone)]
+pub struct HydaClick {
+    pub entity: Entity,
+    pub element_id: Option<String>,
+    pub classes: Vec<String>,
+    pub attributes: HashMap<String, String>,
+}
+
+impl HydaClick {
+    // A click on "entity", with what it needs from its HydaClickable.
+    pub(crate) fn new(entity: Entity, clickable: &HydaClickable) -> Self {
+        Self {
+            entity: entity,
+            element_id: clickable.attributes.get("id").cloned(),
+            classes: clickable.attributes.get("class").map(|c| c.split_whitespace().map(|s| s.to_string()).collect()).unwrap_or_default(),
+            attributes: clickable.attributes.clone(),
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct HydaClickable {
+    tag_name: String,
+    attributes: HashMap<String, String>,
+    pressed: bool,
+}
+
+impl HydaClickable {
+    pub fn new(tag_name: String, attributes: HashMap<String, String>) -> Self {
+        Self {
+            tag_name: tag_name,
+            attributes: attributes,
+            pressed: false,
+        }
+    }
+
+    pub fn tag_name(&self) -> &str {
+        return &self.tag_name;
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        return self.attributes.contains_key("disabled");
+    }
+
+    pub(crate) fn set_attributes(&mut self, attributes: HashMap<String, String>) {
+        self.attributes = attributes;
+    }
+}
+
+pub fn is_element_clickable(tag: &str, attributes: &HashMap<String, String>) -> bool {
+
+    // Same for handlers bound with "data-hyda-on-click", see get_handler_names().
+    if attributes.contains_key("onclick") || attributes.contains_key("data-hyda-on-click") {
+        return true;
+    }
+
+    match tag {
+        "button" | "a" | "label" => true,
+        "input" => {
+            match attributes.get("type").map(|t| t.to_lowercase()) {
+                Some(t) => t == "button" || t == "submit" || t == "checkbox" || t == "radio",
+                None => false,
+            }
+        },
+        _ => false,
+    }
+}
+
+// Same as browsers, a click is pressing and releasing the mouse while on top of the element.
+pub(crate) fn emit_clicks(
+    mut click_events: EventWriter<HydaClick>,
+    mut query_clickable: Query<(Entity, &Interaction, &mut HydaClickable), Changed<Interaction>>,
+) {
+    for (entity, interaction, mut clickable) in &mut query_clickable {
+        match interaction {
+            Interaction::Pressed => clickable.pressed = true,
+            Interaction::Hovered => {
+                if clickable.pressed && !clickable.is_disabled() {
+                    click_events.send(HydaClick::new(entity, &clickable));
+                }
+
+                clickable.pressed = false;
+            },
+            Interaction::None => clickable.pressed = false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_app, spawn_html};
+    use crate::HydaHandlersAppExt;
+
+    #[derive(Resource, Default)]
+    struct Opened(u32);
+
+    #[test]
+    fn data_hyda_on_click_runs_the_handler() {
+        let mut app = test_app();
+        app.add_event::<HydaClick>();
+        app.init_resource::<crate::HydaHandlers>();
+        app.init_resource::<Opened>();
+        app.add_systems(Update, (emit_clicks, crate::handlers::run_click_handlers).chain());
+        app.add_hyda_handler_system("open_settings", |mut opened: ResMut<Opened>| opened.0 += 1);
+
+        spawn_html(&mut app, "<div data-hyda-on-click=\"open_settings\">Settings</div>");
+
+        let world = app.world_mut();
+        let entity = world.query_filtered::<Entity, With<HydaClickable>>().single(world);
+
+        *app.world_mut().get_mut::<Interaction>(entity).unwrap() = Interaction::Pressed;
+        app.update();
+        *app.world_mut().get_mut::<Interaction>(entity).unwrap() = Interaction::Hovered;
+        app.update();
+
+        assert_eq!(app.world().resource::<Opened>().0, 1);
+    }
+}
@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node as HtmlNode, Selector};
+use scraper::node::Text as HtmlText;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use crate::resolve_relative_path;
+use crate::dom::new_html_element;
+
+// The attribute that elements coming from a component get, so the CSS of that component only matches them.
+pub(crate) const HYDA_SCOPE_ATTRIBUTE: &str = "data-hyda-scope";
+
+// Nested components are expanded up to this depth, in case one ends up using itself.
+const MAX_COMPONENT_DEPTH: usize = 32;
+
+// A <hyda-component name="card"> from a file imported with <link rel="component" href="card.html">.
+// Every <card> in the page gets replaced by its content, with "{{prop}}" placeholders filled from
+// the attributes of the <card> and its children put in the <slot>s.
+pub(crate) struct HydaComponent {
+    name: String,
+    html: Arc<Html>,
+    node_id: NodeId,
+    style: String,
+}
+
+impl HydaComponent {
+    pub(crate) fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub(crate) fn style(&self) -> &str {
+        return &self.style;
+    }
+}
+
+fn get_attributes(element: &scraper::node::Element) -> HashMap<String, String> {
+    return element.attrs().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+}
+
+fn is_component_link(element: &ElementRef) -> bool {
+    return element.value().attr("rel").map(|r| r.eq_ignore_ascii_case("component")).unwrap_or(false);
+}
+
+fn load_component_file(path: &str, components: &mut Vec<HydaComponent>, loaded: &mut Vec<String>) {
+
+    if loaded.iter().any(|l| l == path) {
+        return;
+    }
+
+    loaded.push(path.to_string());
+
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("bevy_hyda: couldn't load the components in \"{}\": {}", path, e);
+            return;
+        },
+    };
+
+    let html = Html::parse_fragment(&contents);
+
+    // Components can import other components too.
+    let link_selector = Selector::parse("link[href]").unwrap();
+
+    let links: Vec<String> = html.select(&link_selector)
+        .filter(is_component_link)
+        .map(|l| resolve_relative_path(path, l.value().attr("href").unwrap()).to_string_lossy().to_string())
+        .collect();
+
+    for l in links {
+        load_component_file(&l, components, loaded);
+    }
+
+    let component_selector = Selector::parse("hyda-component[name]").unwrap();
+    let style_selector = Selector::parse("style").unwrap();
+
+    let definitions: Vec<(String, NodeId, String)> = html.select(&component_selector)
+        .map(|c| (
+            c.value().attr("name").unwrap().to_lowercase(),
+            c.id(),
+            c.select(&style_selector).map(|s| s.text().collect::<String>()).collect::<Vec<String>>().join("\n"),
+        ))
+        .collect();
+
+    // A file can have more than one, they share its parsed HTML.
+    let shared_html = Arc::new(html);
+
+    for (name, node_id, style) in definitions {
+        let component = HydaComponent {
+            name: name,
+            html: shared_html.clone(),
+            node_id: node_id,
+            style: style,
+        };
+
+        // Defining one again replaces it, but it keeps its place.
+        match components.iter_mut().find(|c| c.name == component.name) {
+            Some(c) => *c = component,
+            None => components.push(component),
+        }
+    }
+}
+
+// All the components imported by "document" (and the ones they import), in the order they were loaded
+// (which is also the order their CSS goes in).
+pub(crate) fn load_components(document: &Html, url: &str) -> Vec<HydaComponent> {
+
+    let mut components: Vec<HydaComponent> = Vec::new();
+    let mut loaded: Vec<String> = Vec::new();
+
+    let link_selector = Selector::parse("link[href]").unwrap();
+
+    for l in document.select(&link_selector).filter(is_component_link) {
+        let path = resolve_relative_path(url, l.value().attr("href").unwrap());
+
+        load_component_file(&path.to_string_lossy(), &mut components, &mut loaded);
+    }
+
+    return components;
+}
+
+// Replaces "{{name}}" with the value of the "name" attribute of the instance, other placeholders (bindings) stay.
+fn fill_props(text: &str, props: &HashMap<String, String>) -> String {
+
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break; };
+
+        let key = rest[start + 2..start + end].trim();
+
+        result.push_str(&rest[..start]);
+
+        match props.get(key) {
+            Some(v) => result.push_str(v),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+
+        rest = &rest[start + end + 2..];
+    }
+
+    result.push_str(rest);
+
+    return result;
+}
+
+struct HydaComponentInstance<'a> {
+    component: &'a HydaComponent,
+    props: HashMap<String, String>,
+    // The children of the instance, by the name of the slot they go in ("" for the default one).
+    slotted: HashMap<String, Vec<NodeId>>,
+}
+
+impl HydaComponentInstance<'_> {
+    // Copies "node" (from the component) into "document", returns the new (detached) nodes.
+    fn copy_node(&mut self, document: &mut Html, node: NodeRef<HtmlNode>) -> Vec<NodeId> {
+
+        let value = match node.value() {
+            HtmlNode::Element(e) => {
+                // Its CSS is loaded on its own, scoped to the instances.
+                if &*e.name.local == "style" {
+                    return Vec::new();
+                }
+
+                if &*e.name.local == "slot" {
+                    let slot_name = e.attr("name").unwrap_or("").to_string();
+
+                    if let Some(s) = self.slotted.remove(&slot_name) {
+                        return s;
+                    }
+
+                    // Nothing for this slot, so its own content is used instead.
+                    return node.children().flat_map(|c| self.copy_node(document, c)).collect();
+                }
+
+                let mut attributes: HashMap<String, String> = e.attrs().map(|(k, v)| (k.to_string(), fill_props(v, &self.props))).collect();
+                attributes.insert(HYDA_SCOPE_ATTRIBUTE.to_string(), self.component.name.clone());
+
+                HtmlNode::Element(new_html_element(e.name.clone(), &attributes))
+            },
+            HtmlNode::Text(t) => HtmlNode::Text(HtmlText { text: fill_props(&t.text, &self.props).into() }),
+            other => other.clone(),
+        };
+
+        let new_id = document.tree.orphan(value).id();
+
+        for c in node.children() {
+            for n in self.copy_node(document, c) {
+                document.tree.get_mut(new_id).unwrap().append_id(n);
+            }
+        }
+
+        return vec![new_id];
+    }
+}
+
+fn expand_instance(document: &mut Html, instance_id: NodeId, component: &HydaComponent) {
+
+    let Some(instance) = document.tree.get(instance_id) else { return; };
+    let HtmlNode::Element(instance_element) = instance.value() else { return; };
+
+    let props = get_attributes(instance_element);
+
+    let mut slotted: HashMap<String, Vec<NodeId>> = HashMap::new();
+
+    for c in instance.children() {
+        let slot_name = match c.value() {
+            HtmlNode::Element(e) => e.attr("slot").unwrap_or("").to_string(),
+            _ => String::new(),
+        };
+
+        slotted.entry(slot_name).or_default().push(c.id());
+    }
+
+    let mut component_instance = HydaComponentInstance { component: component, props: props.clone(), slotted: slotted };
+
+    let definition = component.html.tree.get(component.node_id).unwrap();
+
+    let roots: Vec<NodeId> = definition.children().flat_map(|c| component_instance.copy_node(document, c)).collect();
+
+    // With a single root element, the "id" and "class" of the instance go to it (so they can be styled and found).
+    let root_elements: Vec<NodeId> = roots.iter().copied().filter(|r| document.tree.get(*r).unwrap().value().is_element()).collect();
+
+    if let [root] = root_elements.as_slice() {
+        if let HtmlNode::Element(e) = document.tree.get(*root).unwrap().value() {
+            let mut attributes = get_attributes(e);
+
+            if let Some(id) = props.get("id") {
+                attributes.insert("id".to_string(), id.clone());
+            }
+
+            if let Some(class) = props.get("class") {
+                let classes = attributes.get("class").map(|c| format!("{} {}", c, class)).unwrap_or(class.clone());
+                attributes.insert("class".to_string(), classes);
+            }
+
+            let name = e.name.clone();
+            *document.tree.get_mut(*root).unwrap().value() = HtmlNode::Element(new_html_element(name, &attributes));
+        }
+    }
+
+    for r in roots {
+        document.tree.get_mut(instance_id).unwrap().insert_id_before(r);
+    }
+
+    // Whatever wasn't used by any slot goes away with the instance.
+    document.tree.get_mut(instance_id).unwrap().detach();
+}
+
+// Replaces every instance of a component in "document" with its content, before anything gets styled.
+pub(crate) fn expand_components(document: &mut Html, components: &Vec<HydaComponent>) {
+
+    if components.is_empty() {
+        return;
+    }
+
+    for _ in 0..MAX_COMPONENT_DEPTH {
+        let instances: Vec<(NodeId, &HydaComponent)> = document.tree.root().descendants()
+            .filter_map(|n| match n.value() {
+                HtmlNode::Element(e) => components.iter().find(|c| c.name == &*e.name.local).map(|c| (n.id(), c)),
+                _ => None,
+            })
+            .collect();
+
+        if instances.is_empty() {
+            return;
+        }
+
+        for (id, component) in instances {
+            expand_instance(document, id, component);
+        }
+    }
+
+    warn!("bevy_hyda: components nested more than {} levels deep, the rest weren't expanded", MAX_COMPONENT_DEPTH);
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemState;
+
+use crate::{HydaAST, HydaBindings, HydaDocument, HydaElement, HydaElementSource, parse_scraper_node, spawn_text_node};
+use crate::dom::{HydaElementLocation, get_parent_element, get_parent_style, modify_attributes};
+
+// "key" is true unless it's missing, empty, "false" or "0". "!key" is the opposite.
+pub fn is_condition_true(condition: &str, bindings: &HydaBindings) -> bool {
+
+    let condition = condition.trim();
+
+    if let Some(c) = condition.strip_prefix('!') {
+        return !is_condition_true(c, bindings);
+    }
+
+    return match bindings.get(condition) {
+        Some(v) => !(v.is_empty() || v == "false" || v == "0"),
+        None => false,
+    };
+}
+
+// Spawned (hidden) in place of an element with "data-if", the element itself is only spawned while the condition is true.
+#[derive(Component, Debug, Clone)]
+pub struct HydaConditional {
+    condition: String,
+    source: HydaElementSource,
+    element: Option<Entity>,
+}
+
+impl HydaConditional {
+    pub(crate) fn new(condition: &str, source: HydaElementSource) -> Self {
+        Self {
+            condition: condition.to_string(),
+            source: source,
+            element: None,
+        }
+    }
+
+    pub fn condition(&self) -> &str {
+        return &self.condition;
+    }
+
+    // The spawned element, if the condition is true.
+    pub fn element(&self) -> Option<Entity> {
+        return self.element;
+    }
+}
+
+// On elements spawned by a HydaConditional, so they go away along with it.
+#[derive(Component, Debug, Clone)]
+pub struct HydaConditionalOf(pub Entity);
+
+// On elements with "data-show", they get the "hidden" attribute (display: none) while the condition is false.
+#[derive(Component, Debug, Clone)]
+pub struct HydaShow {
+    condition: String,
+}
+
+impl HydaShow {
+    pub fn new(condition: &str) -> Self {
+        Self {
+            condition: condition.to_string(),
+        }
+    }
+
+    pub fn condition(&self) -> &str {
+        return &self.condition;
+    }
+}
+
+fn get_document(world: &mut World, source: &HydaElementSource) -> Option<Entity> {
+    let mut query_documents = world.query::<(Entity, &HydaDocument)>();
+
+    return query_documents.iter(world).find(|(_, d)| std::sync::Arc::ptr_eq(d.html(), source.html())).map(|(e, _)| e);
+}
+
+fn spawn_conditional_element(world: &mut World, marker: Entity, source: &HydaElementSource) -> Option<Entity> {
+
+    let document = get_document(world, source)?;
+    let location = HydaElementLocation { html: source.html().clone(), node_id: source.node_id(), document: document };
+
+    let parent = world.get::<Parent>(marker).map(|p| p.get());
+    let parent_element = get_parent_element(world, &location).unwrap_or(Entity::PLACEHOLDER);
+    let parent_style = get_parent_style(world, &location);
+    let styles = world.get::<HydaDocument>(document).unwrap().styles.clone();
+
+    let mut ast = {
+        let html = source.html().lock().unwrap();
+        parse_scraper_node(html.tree.get(source.node_id())?, &styles, &parent_style, source.html())
+    };
+
+    // Otherwise it would spawn another HydaConditional.
+    if let HydaAST::HElement { attributes, .. } = &mut ast {
+        attributes.remove("data-if");
+    }
+
+    let mut system_state: SystemState<(Commands, Res<AssetServer>)> = SystemState::new(world);
+
+    let element = {
+        let (mut commands, asset_server) = system_state.get_mut(world);
+
+        let mut text_sections: Vec<(TextSection, Entity)> = Vec::new();
+
+        let (e, _) = ast.spawn_ui_impl(&mut commands, &asset_server, parent_element, &parent_style, &mut text_sections);
+
+        commands.entity(e).insert(HydaConditionalOf(marker));
+
+        // Inline elements only have text sections, they go in a text node of their own.
+        if !text_sections.is_empty() {
+            let text = spawn_text_node(&mut commands, parent_element, &mut text_sections);
+            commands.entity(text).insert(HydaConditionalOf(marker));
+        }
+
+        e
+    };
+
+    system_state.apply(world);
+
+    if let Some(p) = parent {
+        let index = world.get::<Children>(p).and_then(|c| c.iter().position(|e| *e == marker)).unwrap_or(0);
+
+        let mut query_of = world.query::<(Entity, &HydaConditionalOf)>();
+        let spawned: Vec<Entity> = query_of.iter(world).filter(|(_, o)| o.0 == marker).map(|(e, _)| e).filter(|e| world.get::<Node>(*e).is_some()).collect();
+
+        world.entity_mut(p).insert_children(index + 1, &spawned);
+    }
+
+    return Some(element);
+}
+
+fn despawn_conditional_element(world: &mut World, marker: Entity) {
+
+    let mut query_of = world.query::<(Entity, &HydaConditionalOf)>();
+    let spawned: Vec<Entity> = query_of.iter(world).filter(|(_, o)| o.0 == marker).map(|(e, _)| e).collect();
+
+    for e in spawned {
+        if world.get_entity(e).is_some() {
+            world.entity_mut(e).despawn_recursive();
+        }
+    }
+}
+
+pub(crate) fn update_conditions(world: &mut World) {
+
+    let bindings_changed = world.resource_ref::<HydaBindings>().is_changed();
+    let bindings = world.resource::<HydaBindings>().clone();
+
+    // data-if
+    let mut query_conditionals = world.query::<(Entity, Ref<HydaConditional>)>();
+
+    let conditionals: Vec<(Entity, HydaConditional)> = query_conditionals.iter(world)
+        .filter(|(_, c)| bindings_changed || c.is_added())
+        .map(|(e, c)| (e, c.clone()))
+        .collect();
+
+    for (marker, conditional) in conditionals {
+        let is_true = is_condition_true(&conditional.condition, &bindings);
+
+        if is_true == conditional.element.is_some() {
+            continue;
+        }
+
+        let element = if is_true {
+            spawn_conditional_element(world, marker, &conditional.source)
+        }
+        else {
+            despawn_conditional_element(world, marker);
+            None
+        };
+
+        if let Some(mut c) = world.get_mut::<HydaConditional>(marker) {
+            c.element = element;
+        }
+    }
+
+    // Elements whose HydaConditional isn't there anymore (like when its parent got despawned).
+    let mut query_of = world.query::<(Entity, &HydaConditionalOf)>();
+
+    let orphans: Vec<Entity> = query_of.iter(world).filter(|(_, o)| world.get_entity(o.0).is_none()).map(|(e, _)| e).collect();
+
+    for e in orphans {
+        world.entity_mut(e).despawn_recursive();
+    }
+
+    // data-show
+    let mut query_show = world.query::<(Entity, Ref<HydaShow>, &HydaElement)>();
+
+    let changes: Vec<(Entity, bool)> = query_show.iter(world)
+        .filter(|(_, s, _)| bindings_changed || s.is_added())
+        .map(|(e, s, element)| (e, is_condition_true(&s.condition, &bindings), element.attributes.contains_key("hidden")))
+        .filter(|(_, visible, hidden)| visible == hidden)
+        .map(|(e, visible, _)| (e, visible))
+        .collect();
+
+    for (entity, visible) in changes {
+        modify_attributes(world, entity, |attrs| {
+            if visible {
+                attrs.remove("hidden");
+            }
+            else {
+                attrs.insert("hidden".to_string(), String::new());
+            }
+        });
+    }
+}
@@ -0,0 +1,500 @@
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::gamepad::{Gamepads, GamepadButton, GamepadButtonType};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::ui::RelativeCursorPosition;
+use std::collections::HashMap;
+
+use crate::{BevyHydaStyle, HydaClick, HydaClickable, HydaDocument, HydaFocused, HydaInlineIn, get_document_root};
+use crate::focus::gamepad_just_pressed;
+use crate::navigation::find_element_by_id;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HydaCheckableKind {
+    Checkbox,
+    Radio,
+}
+
+// Sent when a checkbox or radio gets checked or unchecked by the user.
+// Radios only send it for the one being checked, like browsers do.
+#[derive(Event, Debug, Clone)]
+pub struct HydaCheckedChanged {
+    pub entity: Entity,
+    pub element_id: Option<String>,
+    pub name: Option<String>,
+    pub value: String,
+    pub checked: bool,
+}
+
+// Sent when the value of an <input type="range"> changes because of the user.
+#[derive(Event, Debug, Clone)]
+pub struct HydaRangeChanged {
+    pub entity: Entity,
+    pub element_id: Option<String>,
+    pub name: Option<String>,
+    pub value: f32,
+}
+
+// <input type="checkbox"> and <input type="radio">.
+#[derive(Component, Debug, Clone)]
+pub struct HydaCheckable {
+    kind: HydaCheckableKind,
+    attributes: HashMap<String, String>,
+    checked: bool,
+    mark: Entity,
+}
+
+impl HydaCheckable {
+    pub fn kind(&self) -> HydaCheckableKind {
+        return self.kind;
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn is_checked(&self) -> bool {
+        return self.checked;
+    }
+
+    // This doesn't uncheck the other radios of the group, clicking or using the keyboard does.
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        return self.attributes.get("name").map(|n| n.as_str());
+    }
+
+    // The "value" attribute, browsers use "on" when there's none.
+    pub fn value(&self) -> &str {
+        return self.attributes.get("value").map(|v| v.as_str()).unwrap_or("on");
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        return self.attributes.contains_key("disabled");
+    }
+
+    // Like browsers, "checked" is only the initial state, changing it later doesn't check or uncheck it.
+    pub(crate) fn set_attributes(&mut self, attributes: HashMap<String, String>) {
+        self.attributes = attributes;
+    }
+}
+
+// <input type="range">, "value" is always between "min" and "max" and snapped to "step".
+#[derive(Component, Debug, Clone)]
+pub struct HydaRange {
+    attributes: HashMap<String, String>,
+    min: f32,
+    max: f32,
+    step: Option<f32>,
+    value: f32,
+    fill: Entity,
+    thumb: Entity,
+    dragging: bool,
+}
+
+impl HydaRange {
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn min(&self) -> f32 {
+        return self.min;
+    }
+
+    pub fn max(&self) -> f32 {
+        return self.max;
+    }
+
+    // None means "step=any".
+    pub fn step(&self) -> Option<f32> {
+        return self.step;
+    }
+
+    pub fn value(&self) -> f32 {
+        return self.value;
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        let mut final_value = value.clamp(self.min, self.max);
+
+        if let Some(s) = self.step {
+            final_value = (self.min + ((final_value - self.min) / s).round() * s).clamp(self.min, self.max);
+        }
+
+        self.value = final_value;
+    }
+
+    // Where the value is between "min" and "max", from 0 to 1.
+    pub fn normalized(&self) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+
+        return (self.value - self.min) / (self.max - self.min);
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        return self.dragging;
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        return self.attributes.contains_key("disabled");
+    }
+
+    // The value stays the same, unless it's out of the new limits.
+    pub(crate) fn set_attributes(&mut self, attributes: HashMap<String, String>) {
+        (self.min, self.max, self.step) = get_range_limits(&attributes);
+        self.attributes = attributes;
+
+        let value = self.value;
+        self.set_value(value);
+    }
+}
+
+pub fn get_checkable_kind(tag_name: &str, attributes: &HashMap<String, String>) -> Option<HydaCheckableKind> {
+    if tag_name != "input" {
+        return None;
+    }
+
+    match attributes.get("type").map(|t| t.to_lowercase()).as_deref() {
+        Some("checkbox") => Some(HydaCheckableKind::Checkbox),
+        Some("radio") => Some(HydaCheckableKind::Radio),
+        _ => None,
+    }
+}
+
+pub fn is_range_input(tag_name: &str, attributes: &HashMap<String, String>) -> bool {
+    return tag_name == "input" && attributes.get("type").map(|t| t.to_lowercase()).as_deref() == Some("range");
+}
+
+// The check mark (or radio dot) fills the content box of the element, using its "color".
+pub(crate) fn spawn_checkable(commands: &mut Commands, entity: Entity, kind: HydaCheckableKind, attributes: &HashMap<String, String>, style: &BevyHydaStyle) {
+
+    let mark = commands.spawn(NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            display: if attributes.contains_key("checked") { Display::Flex } else { Display::None },
+            ..default()
+        },
+        background_color: BackgroundColor(style.color.unwrap()),
+        ..default()
+    }).id();
+
+    if kind == HydaCheckableKind::Radio {
+        commands.entity(mark).insert(BorderRadius::MAX);
+        commands.entity(entity).insert(BorderRadius::MAX);
+    }
+
+    commands.entity(entity).insert(HydaCheckable {
+        kind: kind,
+        attributes: attributes.clone(),
+        checked: attributes.contains_key("checked"),
+        mark: mark,
+    });
+
+    commands.entity(entity).add_child(mark);
+}
+
+// The element itself is the track, with a fill and a round thumb on top (both using its "color").
+// "min", "max" and "step", with the same defaults as browsers.
+fn get_range_limits(attributes: &HashMap<String, String>) -> (f32, f32, Option<f32>) {
+
+    let get_number = |attr: &str| attributes.get(attr).and_then(|v| v.trim().parse::<f32>().ok());
+
+    let min = get_number("min").unwrap_or(0.0);
+    let max = get_number("max").unwrap_or(100.0).max(min);
+    let step = match attributes.get("step").map(|s| s.trim().to_lowercase()).as_deref() {
+        Some("any") => None,
+        _ => Some(get_number("step").filter(|s| *s > 0.0).unwrap_or(1.0)),
+    };
+
+    return (min, max, step);
+}
+
+pub(crate) fn spawn_range(commands: &mut Commands, entity: Entity, attributes: &HashMap<String, String>, style: &BevyHydaStyle) {
+
+    let (min, max, step) = get_range_limits(attributes);
+
+    let fill = commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        background_color: BackgroundColor(style.color.unwrap()),
+        ..default()
+    }).id();
+
+    let thumb = commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Px(16.0),
+                height: Val::Px(16.0),
+                top: Val::Px(-6.0),
+                margin: UiRect::left(Val::Px(-8.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(style.color.unwrap()),
+            border_radius: BorderRadius::MAX,
+            ..default()
+        },
+        Interaction::default(),
+    )).id();
+
+    let mut range = HydaRange {
+        attributes: attributes.clone(),
+        min: min,
+        max: max,
+        step: step,
+        value: min,
+        fill: fill,
+        thumb: thumb,
+        dragging: false,
+    };
+
+    // Browsers start in the middle when there's no value.
+    range.set_value(attributes.get("value").and_then(|v| v.trim().parse::<f32>().ok()).unwrap_or(min + (max - min) / 2.0));
+
+    commands.entity(entity).insert((range, RelativeCursorPosition::default()));
+    commands.entity(entity).add_child(fill);
+    commands.entity(entity).add_child(thumb);
+}
+
+fn find_checkable_descendant(entity: Entity, query_children: &Query<&Children>, query_checkable: &Query<(Entity, &mut HydaCheckable)>) -> Option<Entity> {
+    for c in query_children.iter_descendants(entity) {
+        if query_checkable.contains(c) {
+            return Some(c);
+        }
+    }
+
+    return None;
+}
+
+// Checks (or toggles) "entity", unchecking the other radios with the same name in the same document.
+fn toggle_checkable(
+    entity: Entity,
+    changed_events: &mut EventWriter<HydaCheckedChanged>,
+    query_checkable: &mut Query<(Entity, &mut HydaCheckable)>,
+    query_parent: &Query<&Parent>,
+    query_inline: &Query<&HydaInlineIn>,
+    query_document: &Query<&HydaDocument>,
+) {
+    let Ok((_, checkable)) = query_checkable.get(entity) else { return; };
+
+    if checkable.is_disabled() {
+        return;
+    }
+
+    let kind = checkable.kind;
+    let name = checkable.name().map(|n| n.to_string());
+
+    if kind == HydaCheckableKind::Radio {
+        // Radios can only be unchecked by checking another one.
+        if checkable.checked {
+            return;
+        }
+
+        if let Some(n) = &name {
+            let root = get_document_root(entity, query_parent, query_inline, query_document);
+
+            for (other_entity, mut other) in query_checkable.iter_mut() {
+                if other.kind == HydaCheckableKind::Radio && other.checked && other.name() == Some(n.as_str())
+                    && get_document_root(other_entity, query_parent, query_inline, query_document) == root {
+                    other.checked = false;
+                }
+            }
+        }
+    }
+
+    let (_, mut checkable) = query_checkable.get_mut(entity).unwrap();
+
+    checkable.checked = match kind {
+        HydaCheckableKind::Checkbox => !checkable.checked,
+        HydaCheckableKind::Radio => true,
+    };
+
+    changed_events.send(HydaCheckedChanged {
+        entity: entity,
+        element_id: checkable.attributes.get("id").cloned(),
+        name: name,
+        value: checkable.value().to_string(),
+        checked: checkable.checked,
+    });
+}
+
+// Clicking a checkbox/radio toggles it, and so does clicking a <label> with its id in "for" (or wrapping it).
+pub(crate) fn toggle_on_click(
+    mut click_events: EventReader<HydaClick>,
+    mut changed_events: EventWriter<HydaCheckedChanged>,
+    mut query_checkable: Query<(Entity, &mut HydaCheckable)>,
+    query_clickable: Query<&HydaClickable>,
+    query_named: Query<(Entity, &Name)>,
+    query_children: Query<&Children>,
+    query_parent: Query<&Parent>,
+    query_inline: Query<&HydaInlineIn>,
+    query_document: Query<&HydaDocument>,
+) {
+    let clicks: Vec<Entity> = click_events.read().map(|c| c.entity).collect();
+
+    let mut targets: Vec<Entity> = Vec::new();
+
+    for entity in &clicks {
+        if query_checkable.contains(*entity) {
+            targets.push(*entity);
+            continue;
+        }
+
+        let Ok(clickable) = query_clickable.get(*entity) else { continue; };
+
+        if clickable.tag_name() != "label" {
+            continue;
+        }
+
+        let target = match (clickable.attributes().get("for"), get_document_root(*entity, &query_parent, &query_inline, &query_document)) {
+            (Some(id), Some(root)) => find_element_by_id(root, id, &query_named, &query_parent, &query_inline, &query_document),
+            _ => find_checkable_descendant(*entity, &query_children, &query_checkable),
+        };
+
+        // Clicking the checkbox inside of its label clicks both, it should only toggle once.
+        if let Some(t) = target {
+            if !clicks.contains(&t) && !targets.contains(&t) {
+                targets.push(t);
+            }
+        }
+    }
+
+    for t in targets {
+        toggle_checkable(t, &mut changed_events, &mut query_checkable, &query_parent, &query_inline, &query_document);
+    }
+}
+
+// Space toggles the focused checkbox/radio, the arrows (or the D-pad left/right) move the focused slider.
+pub(crate) fn control_keyboard_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut checked_events: EventWriter<HydaCheckedChanged>,
+    mut range_events: EventWriter<HydaRangeChanged>,
+    mut query_checkable: Query<(Entity, &mut HydaCheckable)>,
+    mut query_range: Query<(Entity, &mut HydaRange), With<HydaFocused>>,
+    query_focused: Query<Entity, With<HydaFocused>>,
+    query_parent: Query<&Parent>,
+    query_inline: Query<&HydaInlineIn>,
+    query_document: Query<&HydaDocument>,
+) {
+    let mut keys: Vec<Key> = keyboard_events.read().filter(|e| e.state == ButtonState::Pressed).map(|e| e.logical_key.clone()).collect();
+
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadLeft) { keys.push(Key::ArrowLeft); }
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadRight) { keys.push(Key::ArrowRight); }
+
+    for key in keys {
+        for focused in &query_focused {
+            if key == Key::Space && query_checkable.contains(focused) {
+                toggle_checkable(focused, &mut checked_events, &mut query_checkable, &query_parent, &query_inline, &query_document);
+            }
+
+            let Ok((entity, mut range)) = query_range.get_mut(focused) else { continue; };
+
+            if range.is_disabled() {
+                continue;
+            }
+
+            let step = range.step.unwrap_or((range.max - range.min) / 100.0);
+
+            let new_value = match key {
+                Key::ArrowRight | Key::ArrowUp => range.value + step,
+                Key::ArrowLeft | Key::ArrowDown => range.value - step,
+                Key::Home => range.min,
+                Key::End => range.max,
+                _ => continue,
+            };
+
+            let old_value = range.value;
+            range.set_value(new_value);
+
+            if range.value != old_value {
+                send_range_changed(entity, &range, &mut range_events);
+            }
+        }
+    }
+}
+
+fn send_range_changed(entity: Entity, range: &HydaRange, range_events: &mut EventWriter<HydaRangeChanged>) {
+    range_events.send(HydaRangeChanged {
+        entity: entity,
+        element_id: range.attributes.get("id").cloned(),
+        name: range.attributes.get("name").cloned(),
+        value: range.value,
+    });
+}
+
+// Pressing anywhere on the slider (or its thumb) jumps there, and keeps following the mouse until it's released.
+pub(crate) fn drag_ranges(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut range_events: EventWriter<HydaRangeChanged>,
+    mut query_range: Query<(Entity, &Interaction, &RelativeCursorPosition, &mut HydaRange)>,
+    query_interaction: Query<&Interaction, Without<HydaRange>>,
+) {
+    for (entity, interaction, cursor, mut range) in &mut query_range {
+        if !mouse.pressed(MouseButton::Left) {
+            if range.dragging {
+                range.dragging = false;
+            }
+
+            continue;
+        }
+
+        let thumb_pressed = query_interaction.get(range.thumb).map(|i| *i == Interaction::Pressed).unwrap_or(false);
+
+        if mouse.just_pressed(MouseButton::Left) && (*interaction == Interaction::Pressed || thumb_pressed) && !range.is_disabled() {
+            range.dragging = true;
+        }
+
+        if !range.dragging {
+            continue;
+        }
+
+        let Some(normalized) = cursor.normalized else { continue; };
+
+        let old_value = range.value;
+        let new_value = range.min + normalized.x.clamp(0.0, 1.0) * (range.max - range.min);
+
+        range.set_value(new_value);
+
+        if range.value != old_value {
+            send_range_changed(entity, &range, &mut range_events);
+        }
+    }
+}
+
+pub(crate) fn update_controls(
+    query_checkable: Query<&HydaCheckable, Changed<HydaCheckable>>,
+    query_range: Query<&HydaRange, Changed<HydaRange>>,
+    mut query_style: Query<&mut Style>,
+) {
+    for checkable in &query_checkable {
+        if let Ok(mut style) = query_style.get_mut(checkable.mark) {
+            let display = if checkable.checked { Display::Flex } else { Display::None };
+
+            if style.display != display {
+                style.display = display;
+            }
+        }
+    }
+
+    for range in &query_range {
+        let percent = Val::Percent(range.normalized() * 100.0);
+
+        if let Ok(mut style) = query_style.get_mut(range.fill) {
+            style.width = percent;
+        }
+
+        if let Ok(mut style) = query_style.get_mut(range.thumb) {
+            style.left = percent;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{BevyHydaStyle, HydaComputedStyle, HydaElement};
+
+pub type HydaCustomElementSpawner = Arc<dyn Fn(&HashMap<String, String>, &BevyHydaStyle, &mut EntityWorldMut) + Send + Sync>;
+
+// Tags like <health-bar max="100"> or <minimap> that spawn components of your own, filled from
+// "BevyHydaPlugin::with_custom_element()". The element keeps its HydaElement (and HTML children),
+// the components returned by the callback are added on top of (or replacing) its NodeBundle.
+// If they replace its Style, the element gets HydaCustomStyle and CSS won't touch its layout anymore.
+#[derive(Resource, Default, Clone)]
+pub struct HydaCustomElements {
+    spawners: HashMap<String, HydaCustomElementSpawner>,
+}
+
+impl HydaCustomElements {
+    // "spawner" gets the attributes and the computed style of the element, and returns the bundle to insert.
+    pub fn register<B: Bundle, F: Fn(&HashMap<String, String>, &BevyHydaStyle) -> B + Send + Sync + 'static>(&mut self, tag: &str, spawner: F) {
+        self.register_arc(tag, Arc::new(move |attributes: &HashMap<String, String>, style: &BevyHydaStyle, entity: &mut EntityWorldMut| {
+            entity.insert(spawner(attributes, style));
+        }));
+    }
+
+    // Same, but spawning an entity of its own (with whatever hierarchy it needs), which ends up as a child of the element.
+    pub fn register_entity<F: Fn(&HashMap<String, String>, &BevyHydaStyle, &mut World) -> Entity + Send + Sync + 'static>(&mut self, tag: &str, spawner: F) {
+        self.register_arc(tag, Arc::new(move |attributes: &HashMap<String, String>, style: &BevyHydaStyle, entity: &mut EntityWorldMut| {
+            let child = entity.world_scope(|world| spawner(attributes, style, world));
+            entity.add_child(child);
+        }));
+    }
+
+    pub(crate) fn register_arc(&mut self, tag: &str, spawner: HydaCustomElementSpawner) {
+        self.spawners.insert(tag.to_lowercase(), spawner);
+    }
+
+    pub fn remove(&mut self, tag: &str) {
+        self.spawners.remove(&tag.to_lowercase());
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        return self.spawners.contains_key(&tag.to_lowercase());
+    }
+
+    pub fn get(&self, tag: &str) -> Option<&HydaCustomElementSpawner> {
+        return self.spawners.get(&tag.to_lowercase());
+    }
+}
+
+// On custom elements whose spawner replaced their Style: restyles (and pseudo-classes) leave it alone from then on,
+// so the layout of the widget isn't lost. Only their colors and text keep following the CSS.
+#[derive(Component, Debug, Clone, Default)]
+pub struct HydaCustomStyle;
+
+fn spawn_custom_element(world: &mut World, entity: Entity, spawner: &HydaCustomElementSpawner) {
+
+    let Some(element) = world.get::<HydaElement>(entity) else { return; };
+
+    let attributes = element.attributes.clone();
+    let style = world.get::<HydaComputedStyle>(entity).map(|c| c.style().clone()).unwrap_or_default();
+    let old_style = world.get::<Style>(entity).cloned();
+
+    spawner(&attributes, &style, &mut world.entity_mut(entity));
+
+    if world.get::<Style>(entity).cloned() != old_style {
+        world.entity_mut(entity).insert(HydaCustomStyle);
+    }
+}
+
+// Runs the spawners of the elements (with a node) spawned since the last time, for the tags that have one.
+pub(crate) fn spawn_custom_elements(world: &mut World) {
+
+    let Some(custom_elements) = world.get_resource::<HydaCustomElements>().cloned() else { return; };
+
+    if custom_elements.spawners.is_empty() {
+        return;
+    }
+
+    let mut query_added = world.query_filtered::<(Entity, &HydaElement), (Added<HydaElement>, With<Node>)>();
+
+    let added: Vec<(Entity, HydaCustomElementSpawner)> = query_added.iter(world)
+        .filter_map(|(e, element)| custom_elements.get(&element.tag).map(|s| (e, s.clone())))
+        .collect();
+
+    for (entity, spawner) in added {
+        spawn_custom_element(world, entity, &spawner);
+    }
+}
@@ -0,0 +1,526 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemState;
+use ego_tree::{NodeId, NodeRef};
+use markup5ever::{Attribute, LocalName, QualName, namespace_url, ns};
+use scraper::{Html, Node as HtmlNode};
+use scraper::node::{Element, Text as HtmlText};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{BevyHydaStyle, HydaCheckable, HydaClickable, HydaComputedStyle, HydaDocument, HydaElement, HydaFocusable, HydaFocused, HydaHtml, HydaInput, HydaRange, HydaRestyle, parse_scraper_node};
+use crate::{get_checkable_kind, get_focusable, get_input_kind, is_element_clickable, is_range_input};
+use crate::restyle::refresh_selectors;
+use crate::template::{detach_template_instances, replace_instance_entity, restore_template_instances};
+
+// Changes to spawned documents, they update the parsed HTML and then the Bevy nodes:
+// attribute and class changes restyle the element (and its descendants) in place,
+// while changes to the content respawn the closest block element holding it.
+pub trait HydaDomCommandsExt {
+    fn set_text(&mut self, entity: Entity, text: &str);
+    fn set_attribute(&mut self, entity: Entity, name: &str, value: &str);
+    fn remove_attribute(&mut self, entity: Entity, name: &str);
+    fn add_class(&mut self, entity: Entity, class: &str);
+    fn remove_class(&mut self, entity: Entity, class: &str);
+    fn toggle_class(&mut self, entity: Entity, class: &str);
+    fn append_html(&mut self, entity: Entity, html: &str);
+    fn remove_element(&mut self, entity: Entity);
+}
+
+impl HydaDomCommandsExt for Commands<'_, '_> {
+    fn set_text(&mut self, entity: Entity, text: &str) {
+        let final_text = text.to_string();
+        self.add(move |world: &mut World| set_text(world, entity, &final_text));
+    }
+
+    fn set_attribute(&mut self, entity: Entity, name: &str, value: &str) {
+        let (final_name, final_value) = (name.to_lowercase(), value.to_string());
+        self.add(move |world: &mut World| modify_attributes(world, entity, |attrs| { attrs.insert(final_name, final_value); }));
+    }
+
+    fn remove_attribute(&mut self, entity: Entity, name: &str) {
+        let final_name = name.to_lowercase();
+        self.add(move |world: &mut World| modify_attributes(world, entity, |attrs| { attrs.remove(&final_name); }));
+    }
+
+    fn add_class(&mut self, entity: Entity, class: &str) {
+        let final_class = class.to_string();
+        self.add(move |world: &mut World| modify_attributes(world, entity, |attrs| change_class(attrs, &final_class, Some(true))));
+    }
+
+    fn remove_class(&mut self, entity: Entity, class: &str) {
+        let final_class = class.to_string();
+        self.add(move |world: &mut World| modify_attributes(world, entity, |attrs| change_class(attrs, &final_class, Some(false))));
+    }
+
+    fn toggle_class(&mut self, entity: Entity, class: &str) {
+        let final_class = class.to_string();
+        self.add(move |world: &mut World| modify_attributes(world, entity, |attrs| change_class(attrs, &final_class, None)));
+    }
+
+    fn append_html(&mut self, entity: Entity, html: &str) {
+        let final_html = html.to_string();
+        self.add(move |world: &mut World| append_html(world, entity, &final_html));
+    }
+
+    fn remove_element(&mut self, entity: Entity) {
+        self.add(move |world: &mut World| remove_element(world, entity));
+    }
+}
+
+// "add": Some(true) adds it, Some(false) removes it and None toggles it.
+fn change_class(attributes: &mut HashMap<String, String>, class: &str, add: Option<bool>) {
+
+    let mut classes: Vec<String> = attributes.get("class").map(|c| c.split_whitespace().map(|s| s.to_string()).collect()).unwrap_or_default();
+
+    let has_class = classes.iter().any(|c| c == class);
+
+    match (add.unwrap_or(!has_class), has_class) {
+        (true, false) => classes.push(class.to_string()),
+        (false, true) => classes.retain(|c| c != class),
+        _ => return,
+    }
+
+    if classes.is_empty() {
+        attributes.remove("class");
+    }
+    else {
+        attributes.insert("class".to_string(), classes.join(" "));
+    }
+}
+
+// Where an element is: its document (HTML and root entity) and its node.
+pub(crate) struct HydaElementLocation {
+    pub(crate) html: HydaHtml,
+    pub(crate) node_id: NodeId,
+    pub(crate) document: Entity,
+}
+
+pub(crate) fn get_location(world: &mut World, entity: Entity) -> Option<HydaElementLocation> {
+
+    let source = world.get::<HydaElement>(entity)?.source()?.clone();
+
+    let mut query_documents = world.query::<(Entity, &HydaDocument)>();
+
+    let document = query_documents.iter(world).find(|(_, d)| Arc::ptr_eq(d.html(), source.html())).map(|(e, _)| e)?;
+
+    return Some(HydaElementLocation { html: source.html().clone(), node_id: source.node_id(), document: document });
+}
+
+pub(crate) fn get_entity_map(world: &mut World, html: &HydaHtml) -> HashMap<NodeId, Entity> {
+
+    let mut query_elements = world.query::<(Entity, &HydaElement)>();
+
+    return query_elements.iter(world)
+        .filter_map(|(entity, element)| element.source().map(|s| (entity, s)))
+        .filter(|(_, source)| Arc::ptr_eq(source.html(), html))
+        .map(|(entity, source)| (source.node_id(), entity))
+        .collect();
+}
+
+// The element itself if it's a block, otherwise the closest ancestor that is.
+fn get_block_element(world: &mut World, location: &HydaElementLocation) -> Option<Entity> {
+
+    let entities = get_entity_map(world, &location.html);
+    let html = location.html.lock().unwrap();
+
+    let mut current = html.tree.get(location.node_id);
+
+    while let Some(n) = current {
+        if let Some(e) = entities.get(&n.id()) {
+            if world.get::<Node>(*e).is_some() {
+                return Some(*e);
+            }
+        }
+
+        current = n.parent();
+    }
+
+    return None;
+}
+
+// The element "location" is in. Not always its parent in the hierarchy, that can be the content of a scroll container.
+pub(crate) fn get_parent_element(world: &mut World, location: &HydaElementLocation) -> Option<Entity> {
+
+    let entities = get_entity_map(world, &location.html);
+
+    let parent_id = location.html.lock().unwrap().tree.get(location.node_id).and_then(|n| n.parent()).map(|p| p.id());
+
+    return parent_id.and_then(|p| entities.get(&p)).copied();
+}
+
+pub(crate) fn get_parent_style(world: &mut World, location: &HydaElementLocation) -> BevyHydaStyle {
+    return get_parent_element(world, location)
+        .and_then(|e| world.get::<HydaComputedStyle>(e))
+        .map(|c| c.style().clone())
+        .unwrap_or_default();
+}
+
+fn set_node_attributes(html: &mut Html, node_id: NodeId, attributes: &HashMap<String, String>) {
+
+    let Some(mut node) = html.tree.get_mut(node_id) else { return; };
+
+    let HtmlNode::Element(element) = node.value() else { return; };
+
+    // Rebuilding it also resets the id/classes scraper caches for selectors.
+    *element = new_html_element(element.name.clone(), attributes);
+}
+
+pub(crate) fn new_html_element(name: QualName, attributes: &HashMap<String, String>) -> Element {
+
+    let final_attributes: Vec<Attribute> = attributes.iter().map(|(k, v)| Attribute {
+        name: QualName::new(None, ns!(), LocalName::from(k.as_str())),
+        value: v.as_str().into(),
+    }).collect();
+
+    return Element::new(name, final_attributes);
+}
+
+// Selectors around the element can start (or stop) matching after a change to its content,
+// the elements affected by that get restyled at the end of the frame.
+pub(crate) fn refresh_styles(world: &mut World, location: &HydaElementLocation) {
+
+    let entities = get_entity_map(world, &location.html);
+
+    let changed = {
+        let html = location.html.lock().unwrap();
+        let root = html.tree.get(location.node_id).and_then(|n| n.parent()).map(|p| p.id()).unwrap_or(location.node_id);
+
+        let Some(mut document) = world.get_mut::<HydaDocument>(location.document) else { return; };
+
+        refresh_selectors(&html, &mut document.styles, &vec![root])
+    };
+
+    for c in changed {
+        if let Some(e) = entities.get(&c) {
+            world.entity_mut(*e).insert(HydaRestyle);
+        }
+    }
+}
+
+pub(crate) fn modify_attributes<F: FnOnce(&mut HashMap<String, String>)>(world: &mut World, entity: Entity, modify: F) {
+
+    let Some(location) = get_location(world, entity) else {
+        warn!("bevy_hyda: {:?} isn't an element of a spawned document", entity);
+        return;
+    };
+
+    let Some(mut element) = world.get_mut::<HydaElement>(entity) else { return; };
+
+    let old_attributes = element.attributes.clone();
+
+    modify(&mut element.attributes);
+    element.update_from_attributes();
+
+    let tag = element.tag.clone();
+    let attributes = element.attributes.clone();
+    let id = element.id.clone();
+
+    set_node_attributes(&mut location.html.lock().unwrap(), location.node_id, &attributes);
+
+    match id {
+        Some(i) => { world.entity_mut(entity).insert(Name::new(i)); },
+        None => { world.entity_mut(entity).remove::<Name>(); },
+    }
+
+    // Elements that would be spawned with other components (an input changing its type, a <span> getting
+    // an "onclick"...) are spawned again, the others keep their state (like the focus or what was typed).
+    if !has_same_behavior(&tag, &old_attributes, &attributes) {
+        if let Some(block) = get_block_element(world, &location) {
+            respawn_block(world, block);
+        }
+
+        return;
+    }
+
+    update_behavior(world, entity, &tag, &attributes);
+
+    world.entity_mut(entity).insert(HydaRestyle);
+}
+
+fn has_same_behavior(tag: &str, old: &HashMap<String, String>, new: &HashMap<String, String>) -> bool {
+    return is_element_clickable(tag, old) == is_element_clickable(tag, new)
+        && get_input_kind(tag, old) == get_input_kind(tag, new)
+        && get_checkable_kind(tag, old) == get_checkable_kind(tag, new)
+        && is_range_input(tag, old) == is_range_input(tag, new)
+        && ["data-if", "data-show", "data-for"].iter().all(|a| old.get(*a) == new.get(*a));
+}
+
+// The components that keep their own copy of the attributes ("disabled", "href", "min"...) get the new ones.
+fn update_behavior(world: &mut World, entity: Entity, tag: &str, attributes: &HashMap<String, String>) {
+
+    let mut entity_mut = world.entity_mut(entity);
+
+    if let Some(mut clickable) = entity_mut.get_mut::<HydaClickable>() {
+        clickable.set_attributes(attributes.clone());
+    }
+
+    if let Some(mut input) = entity_mut.get_mut::<HydaInput>() {
+        input.set_attributes(attributes.clone());
+    }
+
+    if let Some(mut checkable) = entity_mut.get_mut::<HydaCheckable>() {
+        checkable.set_attributes(attributes.clone());
+    }
+
+    if let Some(mut range) = entity_mut.get_mut::<HydaRange>() {
+        range.set_attributes(attributes.clone());
+    }
+
+    // Inline elements can't get the focus, same as when they're spawned.
+    match get_focusable(tag, attributes).filter(|_| entity_mut.contains::<Node>()) {
+        Some(focusable) => {
+            if !entity_mut.contains::<Interaction>() {
+                entity_mut.insert(Interaction::default());
+            }
+
+            entity_mut.insert(focusable);
+        },
+        // Like disabling the focused element in a browser, it loses the focus.
+        None => { entity_mut.remove::<(HydaFocusable, HydaFocused)>(); },
+    }
+}
+
+// Spawns "block" again from the HTML, in the same place of the hierarchy.
+fn respawn_block(world: &mut World, block: Entity) {
+
+    let Some(location) = get_location(world, block) else { return; };
+
+    refresh_styles(world, &location);
+
+    let parent_style = get_parent_style(world, &location);
+    let styles = world.get::<HydaDocument>(location.document).unwrap().styles.clone();
+
+    let templates = detach_template_instances(world, &location);
+
+    let ast = {
+        let html = location.html.lock().unwrap();
+        let Some(node) = html.tree.get(location.node_id) else { return; };
+
+        parse_scraper_node(node, &styles, &parent_style, &location.html)
+    };
+
+    let mut system_state: SystemState<(Commands, Res<AssetServer>)> = SystemState::new(world);
+    let (mut commands, asset_server) = system_state.get_mut(world);
+
+    let new_block = ast.spawn_ui_impl(&mut commands, &asset_server, Entity::PLACEHOLDER, &parent_style, &mut Vec::new()).0;
+
+    system_state.apply(world);
+
+    restore_template_instances(world, &location.html, templates);
+    replace_instance_entity(world, block, new_block);
+
+    if let Some(parent) = world.get::<Parent>(block).map(|p| p.get()) {
+        let index = world.get::<Children>(parent).and_then(|c| c.iter().position(|e| *e == block)).unwrap_or(0);
+        world.entity_mut(parent).insert_children(index, &[new_block]);
+    }
+
+    if let Some(document) = world.entity_mut(block).take::<HydaDocument>() {
+        world.entity_mut(new_block).insert(document);
+    }
+
+    world.entity_mut(block).despawn_recursive();
+}
+
+fn set_text(world: &mut World, entity: Entity, text: &str) {
+
+    let Some(location) = get_location(world, entity) else {
+        warn!("bevy_hyda: {:?} isn't an element of a spawned document", entity);
+        return;
+    };
+
+    {
+        let mut html = location.html.lock().unwrap();
+        let Some(mut node) = html.tree.get_mut(location.node_id) else { return; };
+
+        while let Some(mut c) = node.first_child() {
+            c.detach();
+        }
+
+        node.append(HtmlNode::Text(HtmlText { text: text.into() }));
+    }
+
+    if let Some(block) = get_block_element(world, &location) {
+        respawn_block(world, block);
+    }
+}
+
+fn append_html(world: &mut World, entity: Entity, html_str: &str) {
+
+    let Some(location) = get_location(world, entity) else {
+        warn!("bevy_hyda: {:?} isn't an element of a spawned document", entity);
+        return;
+    };
+
+    let fragment = Html::parse_fragment(html_str);
+
+    {
+        let mut html = location.html.lock().unwrap();
+
+        // parse_fragment() wraps everything in an <html> element.
+        let fragment_root = fragment.root_element();
+
+        for c in fragment_root.children() {
+            copy_node(&mut html, location.node_id, c);
+        }
+    }
+
+    if let Some(block) = get_block_element(world, &location) {
+        respawn_block(world, block);
+    }
+}
+
+fn copy_node(html: &mut Html, parent: NodeId, node: NodeRef<HtmlNode>) {
+
+    let Some(mut parent_node) = html.tree.get_mut(parent) else { return; };
+
+    let new_id = parent_node.append(node.value().clone()).id();
+
+    for c in node.children() {
+        copy_node(html, new_id, c);
+    }
+}
+
+fn remove_element(world: &mut World, entity: Entity) {
+
+    let Some(location) = get_location(world, entity) else {
+        warn!("bevy_hyda: {:?} isn't an element of a spawned document", entity);
+        return;
+    };
+
+    // Inline elements are part of the text of their block, so that one gets respawned.
+    let is_block = world.get::<Node>(entity).is_some();
+
+    let parent_block = if is_block { None } else { get_block_element(world, &location) };
+
+    if let Some(mut node) = location.html.lock().unwrap().tree.get_mut(location.node_id) {
+        node.detach();
+    }
+
+    match parent_block {
+        Some(b) => respawn_block(world, b),
+        None => {
+            refresh_styles(world, &location);
+            world.entity_mut(entity).despawn_recursive();
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_app, spawn_html};
+
+    fn find_by_tag(world: &mut World, tag: &str) -> Entity {
+        let mut query_elements = world.query::<(Entity, &HydaElement)>();
+        return query_elements.iter(world).find(|(_, e)| e.tag == tag).unwrap().0;
+    }
+
+    fn class_of(attributes: &HashMap<String, String>) -> Option<&str> {
+        return attributes.get("class").map(|c| c.as_str());
+    }
+
+    #[test]
+    fn add_and_remove_classes() {
+        let mut attributes: HashMap<String, String> = HashMap::new();
+
+        change_class(&mut attributes, "item", Some(true));
+        assert_eq!(class_of(&attributes), Some("item"));
+
+        change_class(&mut attributes, "selected", Some(true));
+        change_class(&mut attributes, "selected", Some(true));
+        assert_eq!(class_of(&attributes), Some("item selected"));
+
+        change_class(&mut attributes, "item", Some(false));
+        change_class(&mut attributes, "missing", Some(false));
+        assert_eq!(class_of(&attributes), Some("selected"));
+
+        // No classes left, no attribute.
+        change_class(&mut attributes, "selected", Some(false));
+        assert_eq!(class_of(&attributes), None);
+    }
+
+    #[test]
+    fn toggle_classes() {
+        let mut attributes: HashMap<String, String> = HashMap::from([("class".to_string(), "  item   open ".to_string())]);
+
+        change_class(&mut attributes, "open", None);
+        assert_eq!(class_of(&attributes), Some("item"));
+
+        change_class(&mut attributes, "open", None);
+        assert_eq!(class_of(&attributes), Some("item open"));
+    }
+
+    #[test]
+    fn append_and_remove_elements() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<ul><li>Sword</li></ul>");
+
+        let ul = find_by_tag(app.world_mut(), "ul");
+
+        app.world_mut().commands().append_html(ul, "<li class=\"new\">Shield</li>");
+        app.world_mut().flush();
+
+        let world = app.world_mut();
+        let items: Vec<(Entity, HydaElement)> = world.query::<(Entity, &HydaElement)>().iter(world).filter(|(_, e)| e.tag == "li").map(|(e, h)| (e, h.clone())).collect();
+
+        assert_eq!(items.len(), 2);
+
+        let new_item = items.iter().find(|(_, e)| e.has_class("new")).unwrap().0;
+
+        app.world_mut().commands().remove_element(new_item);
+        app.world_mut().flush();
+
+        let world = app.world_mut();
+        assert_eq!(world.query::<&HydaElement>().iter(world).filter(|e| e.tag == "li").count(), 1);
+        assert!(world.get_entity(new_item).is_none());
+    }
+
+    #[test]
+    fn disabling_a_button_updates_its_components() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<button>Start</button>");
+
+        let button = find_by_tag(app.world_mut(), "button");
+
+        app.world_mut().commands().set_attribute(button, "disabled", "");
+        app.world_mut().flush();
+
+        assert!(app.world().get::<HydaClickable>(button).unwrap().is_disabled());
+        assert!(app.world().get::<HydaFocusable>(button).is_none());
+
+        app.world_mut().commands().remove_attribute(button, "disabled");
+        app.world_mut().flush();
+
+        assert!(!app.world().get::<HydaClickable>(button).unwrap().is_disabled());
+        assert!(app.world().get::<HydaFocusable>(button).is_some());
+    }
+
+    #[test]
+    fn changing_href_updates_the_link() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<p><a href=\"a.html\">Next</a></p>");
+
+        let link = find_by_tag(app.world_mut(), "a");
+
+        app.world_mut().commands().set_attribute(link, "href", "b.html");
+        app.world_mut().flush();
+
+        assert_eq!(app.world().get::<HydaClickable>(link).unwrap().attributes().get("href").map(|h| h.as_str()), Some("b.html"));
+    }
+
+    #[test]
+    fn changing_the_type_of_an_input_respawns_it() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<div><input type=\"text\"></div>");
+
+        let input = find_by_tag(app.world_mut(), "input");
+
+        app.world_mut().commands().set_attribute(input, "type", "checkbox");
+        app.world_mut().flush();
+
+        let new_input = find_by_tag(app.world_mut(), "input");
+
+        assert!(app.world().get::<HydaInput>(new_input).is_none());
+        assert!(app.world().get::<HydaCheckable>(new_input).is_some());
+    }
+}
@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use bevy::ecs::component::{ComponentHooks, StorageType};
+use ego_tree::NodeId;
+use scraper::Html;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::BevyHydaStyle;
+use crate::binding::get_text_binding;
+
+// The parsed HTML of a document, shared by its HydaDocument and all of its elements.
+pub type HydaHtml = Arc<Mutex<Html>>;
+
+// Where an element comes from in the parsed HTML.
+#[derive(Debug, Clone)]
+pub struct HydaElementSource {
+    html: HydaHtml,
+    node_id: NodeId,
+}
+
+impl HydaElementSource {
+    pub(crate) fn new(html: HydaHtml, node_id: NodeId) -> Self {
+        Self {
+            html: html,
+            node_id: node_id,
+        }
+    }
+
+    pub fn html(&self) -> &HydaHtml {
+        return &self.html;
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        return self.node_id;
+    }
+}
+
+// On every entity spawned from an HTML element, so systems can find them by tag, id, class or attribute.
+// Inline elements (<b>, <span>...) get one too, even though their text ends up in the text node of their block.
+#[derive(Component, Debug, Clone)]
+pub struct HydaElement {
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub attributes: HashMap<String, String>,
+    source: Option<HydaElementSource>,
+}
+
+impl HydaElement {
+    pub fn new(tag: &str, attributes: &HashMap<String, String>) -> Self {
+        Self {
+            tag: tag.to_string(),
+            id: attributes.get("id").cloned(),
+            classes: attributes.get("class").map(|c| c.split_whitespace().map(|s| s.to_string()).collect()).unwrap_or_default(),
+            attributes: attributes.clone(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn with_source(mut self, source: Option<HydaElementSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn source(&self) -> Option<&HydaElementSource> {
+        return self.source.as_ref();
+    }
+
+    // Keeps "id" and "classes" in sync after changing the attributes.
+    pub(crate) fn update_from_attributes(&mut self) {
+        self.id = self.attributes.get("id").cloned();
+        self.classes = self.attributes.get("class").map(|c| c.split_whitespace().map(|s| s.to_string()).collect()).unwrap_or_default();
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        return self.classes.iter().any(|c| c == class);
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        return self.attributes.get(name).map(|a| a.as_str());
+    }
+}
+
+// The style an element ended up with after applying all the (non-dynamic) rules, and inheritance.
+#[derive(Component, Debug, Clone)]
+pub struct HydaComputedStyle {
+    style: BevyHydaStyle,
+}
+
+impl HydaComputedStyle {
+    pub(crate) fn new(style: BevyHydaStyle) -> Self {
+        Self {
+            style: style,
+        }
+    }
+
+    pub fn style(&self) -> &BevyHydaStyle {
+        return &self.style;
+    }
+}
+
+// On inline elements, which aren't part of the hierarchy (they don't have a node, their text goes in the
+// text node of their block): the element they're in, so they can be found from their document and go away with it.
+#[derive(Component, Debug, Clone)]
+pub struct HydaInlineIn(pub Entity);
+
+// The parent of "entity" in the hierarchy, or the element it's in for inline elements.
+pub(crate) fn get_hierarchy_parent(entity: Entity, query_parent: &Query<&Parent>, query_inline: &Query<&HydaInlineIn>) -> Option<Entity> {
+    if let Ok(p) = query_parent.get(entity) {
+        return Some(p.get());
+    }
+
+    return query_inline.get(entity).ok().map(|i| i.0);
+}
+
+// The other side of HydaInlineIn, on the elements that have inline elements inside.
+// They get despawned along with it (nested ones too, a <b> in a <span> is in the list of the <span>).
+#[derive(Debug, Clone)]
+pub struct HydaInlineElements(pub Vec<Entity>);
+
+impl Component for HydaInlineElements {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks.on_remove(|mut world, entity, _| {
+            let inline = world.get::<HydaInlineElements>(entity).map(|i| i.0.clone()).unwrap_or_default();
+
+            world.commands().add(move |world: &mut World| {
+                for e in inline {
+                    if let Some(e) = world.get_entity_mut(e) {
+                        e.despawn_recursive();
+                    }
+                }
+            });
+        });
+    }
+}
+
+pub(crate) fn add_inline_element(world: &mut World, element: Entity, inline: Entity) {
+
+    let Some(mut element_mut) = world.get_entity_mut(element) else { return; };
+
+    match element_mut.get_mut::<HydaInlineElements>() {
+        Some(mut i) => i.0.push(inline),
+        None => { element_mut.insert(HydaInlineElements(vec![inline])); },
+    }
+}
+
+// On text nodes, "element" is the block element they're in and "section_elements"
+// the element each one of the sections came from (the same one, or an inline element inside of it).
+#[derive(Component, Debug, Clone)]
+pub struct HydaText {
+    pub element: Entity,
+    pub section_elements: Vec<Entity>,
+}
+
+pub(crate) fn spawn_text_node(commands: &mut Commands, element: Entity, pending_text: &mut Vec<(TextSection, Entity)>) -> Entity {
+
+    let (mut sections, section_elements): (Vec<TextSection>, Vec<Entity>) = pending_text.drain(..).unzip();
+
+    let binding = get_text_binding(&mut sections);
+
+    let result = commands.spawn((
+        TextBundle::from_sections(sections),
+        HydaText { element: element, section_elements: section_elements },
+    )).id();
+
+    if let Some(b) = binding {
+        commands.entity(result).insert(b);
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_app, spawn_html};
+
+    #[test]
+    fn inline_elements_go_away_with_their_block() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<p id=\"greeting\">Hi <b>you <i>there</i></b>!</p>");
+
+        let world = app.world_mut();
+        let (p, b) = {
+            let mut query_elements = world.query::<(Entity, &HydaElement)>();
+            let mut find = |tag: &str| query_elements.iter(world).find(|(_, e)| e.tag == tag).unwrap().0;
+            (find("p"), find("b"))
+        };
+
+        assert_eq!(world.get::<HydaInlineIn>(b).map(|i| i.0), Some(p));
+
+        world.entity_mut(p).despawn_recursive();
+
+        let mut query_elements = world.query::<&HydaElement>();
+        assert!(query_elements.iter(world).all(|e| e.tag != "b" && e.tag != "i"));
+    }
+}
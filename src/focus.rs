@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::gamepad::{Gamepads, GamepadButton, GamepadButtonType};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use std::collections::HashMap;
+
+use crate::{HydaCheckable, HydaClick, HydaClickable, HydaFocused, HydaRange, HydaScrollRequest, HydaSelect};
+
+// Elements that can get the focus with the keyboard or a gamepad (and by clicking them).
+// Only the ones with "tab_index" >= 0 are part of the tab order.
+#[derive(Component, Debug, Clone)]
+pub struct HydaFocusable {
+    pub tab_index: i32,
+    pub autofocus: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HydaFocusDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl HydaFocusDirection {
+    fn as_vec2(&self) -> Vec2 {
+        // UI coordinates go down, same as the window.
+        match self {
+            HydaFocusDirection::Up => Vec2::new(0.0, -1.0),
+            HydaFocusDirection::Down => Vec2::new(0.0, 1.0),
+            HydaFocusDirection::Left => Vec2::new(-1.0, 0.0),
+            HydaFocusDirection::Right => Vec2::new(1.0, 0.0),
+        }
+    }
+}
+
+// Same defaults as browsers: links with href, buttons and form controls, plus anything with a "tabindex".
+pub fn get_focusable(tag_name: &str, attributes: &HashMap<String, String>) -> Option<HydaFocusable> {
+
+    if attributes.contains_key("disabled") {
+        return None;
+    }
+
+    let default_tab_index = match tag_name {
+        "a" => if attributes.contains_key("href") { Some(0) } else { None },
+        "button" | "select" | "textarea" => Some(0),
+        "input" => if attributes.get("type").map(|t| t.to_lowercase()).as_deref() == Some("hidden") { None } else { Some(0) },
+        _ => None,
+    };
+
+    let tab_index = match attributes.get("tabindex").and_then(|t| t.trim().parse::<i32>().ok()) {
+        Some(t) => Some(t),
+        None => default_tab_index,
+    };
+
+    return tab_index.map(|t| HydaFocusable { tab_index: t, autofocus: attributes.contains_key("autofocus") });
+}
+
+pub(crate) fn gamepad_just_pressed(gamepads: &Gamepads, buttons: &ButtonInput<GamepadButton>, button_type: GamepadButtonType) -> bool {
+    return gamepads.iter().any(|g| buttons.just_pressed(GamepadButton::new(g, button_type)));
+}
+
+// Position of the entity among all the others, as the child index on each level of the hierarchy.
+fn get_tree_path(entity: Entity, query_parent: &Query<&Parent>, query_children: &Query<&Children>) -> Vec<usize> {
+
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = entity;
+
+    while let Ok(p) = query_parent.get(current) {
+        let index = query_children.get(p.get()).ok().and_then(|c| c.iter().position(|e| *e == current)).unwrap_or(0);
+
+        path.push(index);
+        current = p.get();
+    }
+
+    path.reverse();
+
+    return path;
+}
+
+fn set_focus(commands: &mut Commands, target: Entity, visible: bool, query_focused: &Query<Entity, With<HydaFocused>>) {
+    for e in query_focused {
+        if e != target {
+            commands.entity(e).remove::<HydaFocused>();
+        }
+    }
+
+    commands.entity(target).insert(HydaFocused { visible: visible });
+}
+
+fn get_visible_rect(entity: Entity, query_node: &Query<(&Node, &GlobalTransform, Option<&ViewVisibility>)>) -> Option<Rect> {
+
+    let (node, transform, visibility) = query_node.get(entity).ok()?;
+
+    if node.size() == Vec2::ZERO || visibility.map(|v| !v.get()).unwrap_or(false) {
+        return None;
+    }
+
+    return Some(node.logical_rect(transform));
+}
+
+// The closest focusable in that direction, the distance to the side counts more than the distance ahead.
+fn find_spatial_target(from: Rect, direction: HydaFocusDirection, candidates: &Vec<(Entity, Rect)>) -> Option<Entity> {
+
+    let dir = direction.as_vec2();
+
+    let mut best: Option<(Entity, f32)> = None;
+
+    for (entity, rect) in candidates {
+        let delta = rect.center() - from.center();
+
+        let ahead = delta.dot(dir);
+
+        if ahead <= 0.0 {
+            continue;
+        }
+
+        let aside = (delta - dir * ahead).length();
+        let score = ahead + aside * 2.0;
+
+        if best.is_none() || score < best.unwrap().1 {
+            best = Some((*entity, score));
+        }
+    }
+
+    return best.map(|b| b.0);
+}
+
+pub(crate) fn autofocus(
+    mut commands: Commands,
+    query_added: Query<(Entity, &HydaFocusable), Added<HydaFocusable>>,
+    query_focused: Query<Entity, With<HydaFocused>>,
+    query_parent: Query<&Parent>,
+    query_children: Query<&Children>,
+) {
+    // If there's more than one, the first one in the document wins (same as browsers).
+    let first = query_added.iter()
+        .filter(|(_, f)| f.autofocus)
+        .map(|(e, _)| (e, get_tree_path(e, &query_parent, &query_children)))
+        .min_by(|a, b| a.1.cmp(&b.1));
+
+    if let Some((entity, _)) = first {
+        set_focus(&mut commands, entity, true, &query_focused);
+    }
+}
+
+// Tab/Shift-Tab go through the tab order, the D-pad moves to the closest element in that direction.
+pub(crate) fn navigate_focus(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut scroll_requests: EventWriter<HydaScrollRequest>,
+    query_focusable: Query<(Entity, &HydaFocusable)>,
+    query_focused: Query<Entity, With<HydaFocused>>,
+    query_select: Query<&HydaSelect>,
+    query_range: Query<(), With<HydaRange>>,
+    query_node: Query<(&Node, &GlobalTransform, Option<&ViewVisibility>)>,
+    query_parent: Query<&Parent>,
+    query_children: Query<&Children>,
+) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let mut tab: Option<bool> = None;
+
+    for event in keyboard_events.read() {
+        if event.state == ButtonState::Pressed && event.logical_key == Key::Tab {
+            tab = Some(!shift);
+        }
+    }
+
+    let focused = query_focused.iter().next();
+
+    // Open <select> popups use the D-pad for their options, and sliders use left/right for their value.
+    let select_open = focused.and_then(|f| query_select.get(f).ok()).map(|s| s.is_open() || s.is_multiple()).unwrap_or(false);
+    let is_range = focused.map(|f| query_range.contains(f)).unwrap_or(false);
+
+    let mut direction: Option<HydaFocusDirection> = None;
+
+    if !select_open {
+        if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadUp) { direction = Some(HydaFocusDirection::Up); }
+        if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadDown) { direction = Some(HydaFocusDirection::Down); }
+    }
+
+    if !is_range {
+        if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadLeft) { direction = Some(HydaFocusDirection::Left); }
+        if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadRight) { direction = Some(HydaFocusDirection::Right); }
+    }
+
+    if tab.is_none() && direction.is_none() {
+        return;
+    }
+
+    // Everything that can be reached, in tab order: positive tabindex first, then document order.
+    let mut candidates: Vec<(Entity, i32, Vec<usize>, Rect)> = query_focusable.iter()
+        .filter(|(_, f)| f.tab_index >= 0)
+        .filter_map(|(e, f)| get_visible_rect(e, &query_node).map(|r| (e, f.tab_index, get_tree_path(e, &query_parent, &query_children), r)))
+        .collect();
+
+    if candidates.is_empty() {
+        return;
+    }
+
+    candidates.sort_by(|a, b| {
+        let order = |t: i32| if t == 0 { i32::MAX } else { t };
+        order(a.1).cmp(&order(b.1)).then(a.2.cmp(&b.2))
+    });
+
+    let current_index = focused.and_then(|f| candidates.iter().position(|c| c.0 == f));
+
+    let target = match (tab, direction, current_index) {
+        (Some(forward), _, Some(i)) => {
+            let len = candidates.len();
+            Some(candidates[if forward { (i + 1) % len } else { (i + len - 1) % len }].0)
+        },
+        (Some(forward), _, None) => Some(if forward { candidates[0].0 } else { candidates[candidates.len() - 1].0 }),
+        (None, Some(d), Some(i)) => {
+            let from = candidates[i].3;
+            let others: Vec<(Entity, Rect)> = candidates.iter().filter(|c| c.0 != candidates[i].0).map(|c| (c.0, c.3)).collect();
+
+            find_spatial_target(from, d, &others)
+        },
+        // Nothing focused yet, the D-pad starts at the beginning.
+        (None, Some(_), None) => Some(candidates[0].0),
+        (None, None, _) => None,
+    };
+
+    if let Some(t) = target {
+        set_focus(&mut commands, t, true, &query_focused);
+        scroll_requests.send(HydaScrollRequest::IntoView { element: t, smooth: None });
+    }
+}
+
+// Enter (or A on a gamepad) clicks the focused element, same as the mouse would.
+// Checkboxes and radios are the exception, like browsers they only toggle with Space (or A).
+pub(crate) fn activate_focused(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut click_events: EventWriter<HydaClick>,
+    query_focused: Query<(Entity, &HydaClickable, Has<HydaCheckable>), With<HydaFocused>>,
+) {
+    let gamepad_activate = gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::South);
+    let mut enter = false;
+
+    for event in keyboard_events.read() {
+        if event.state == ButtonState::Pressed && event.logical_key == Key::Enter {
+            enter = true;
+        }
+    }
+
+    if !enter && !gamepad_activate {
+        return;
+    }
+
+    for (entity, clickable, is_checkable) in &query_focused {
+        if clickable.is_disabled() || (is_checkable && !gamepad_activate) {
+            continue;
+        }
+
+        click_events.send(HydaClick::new(entity, clickable));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HydaElement;
+    use crate::tests::{test_app, spawn_html};
+
+    #[test]
+    fn autofocus_the_first_one() {
+        let mut app = test_app();
+        app.add_systems(Update, autofocus);
+
+        spawn_html(&mut app, "<div><div><input name=\"first\" autofocus></div><input name=\"second\" autofocus><button autofocus>Go</button></div>");
+        app.update();
+
+        let world = app.world_mut();
+        let mut query_focused = world.query_filtered::<&HydaElement, With<HydaFocused>>();
+        let focused: Vec<&HydaElement> = query_focused.iter(world).collect();
+
+        assert_eq!(focused.len(), 1);
+        assert_eq!(focused[0].attribute("name"), Some("first"));
+    }
+}
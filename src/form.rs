@@ -0,0 +1,476 @@
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use std::collections::HashMap;
+
+use crate::{HydaCheckable, HydaCheckableKind, HydaClick, HydaClickable, HydaDocument, HydaElement, HydaFocused, HydaInlineIn, HydaInput, HydaInputKind, HydaRange, HydaSelect, get_document_root};
+
+// Sent when a <form> gets submitted and all of its fields are valid.
+// Fields with the same name (checkboxes, <select multiple>) have their values joined with ",".
+#[derive(Event, Debug, Clone)]
+pub struct HydaFormSubmit {
+    pub entity: Entity,
+    pub form_id: Option<String>,
+    pub action: Option<String>,
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct HydaForm {
+    attributes: HashMap<String, String>,
+}
+
+impl HydaForm {
+    pub fn new(attributes: HashMap<String, String>) -> Self {
+        Self {
+            attributes: attributes,
+        }
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn is_validated(&self) -> bool {
+        return !self.attributes.contains_key("novalidate");
+    }
+}
+
+// Why a field doesn't pass validation, same names as the ValidityState of browsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HydaInvalidReason {
+    ValueMissing,
+    PatternMismatch,
+    RangeUnderflow,
+    RangeOverflow,
+    BadInput,
+}
+
+// On the fields that are currently invalid, which is what :invalid matches.
+#[derive(Component, Debug, Clone)]
+pub struct HydaInvalid {
+    pub reason: HydaInvalidReason,
+}
+
+fn validate_input(input: &HydaInput) -> Option<HydaInvalidReason> {
+
+    let attributes = input.attributes();
+    let value = input.value();
+
+    if value.is_empty() {
+        return if attributes.contains_key("required") { Some(HydaInvalidReason::ValueMissing) } else { None };
+    }
+
+    if input.kind() == HydaInputKind::Number {
+        let Ok(number) = value.trim().parse::<f64>() else { return Some(HydaInvalidReason::BadInput); };
+
+        let get_number = |attr: &str| attributes.get(attr).and_then(|v| v.trim().parse::<f64>().ok());
+
+        if get_number("min").map(|min| number < min).unwrap_or(false) {
+            return Some(HydaInvalidReason::RangeUnderflow);
+        }
+
+        if get_number("max").map(|max| number > max).unwrap_or(false) {
+            return Some(HydaInvalidReason::RangeOverflow);
+        }
+    }
+
+    if input.pattern().map(|p| !p.is_match(value)).unwrap_or(false) {
+        return Some(HydaInvalidReason::PatternMismatch);
+    }
+
+    return None;
+}
+
+// "radio_scope" is the form (or document) the radio is in, its group is only checked by radios in there.
+fn validate_checkable(checkable: &HydaCheckable, radio_scope: Option<Entity>, checked_radio_groups: &Vec<(Option<Entity>, String)>) -> Option<HydaInvalidReason> {
+
+    if !checkable.attributes().contains_key("required") {
+        return None;
+    }
+
+    let is_checked = match (checkable.kind(), checkable.name()) {
+        // One required radio makes the whole group required.
+        (HydaCheckableKind::Radio, Some(n)) => checked_radio_groups.iter().any(|(s, g)| *s == radio_scope && g == n),
+        _ => checkable.is_checked(),
+    };
+
+    return if is_checked { None } else { Some(HydaInvalidReason::ValueMissing) };
+}
+
+fn validate_select(select: &HydaSelect) -> Option<HydaInvalidReason> {
+
+    if select.attributes().contains_key("required") && select.value().map(|v| v.is_empty()).unwrap_or(true) {
+        return Some(HydaInvalidReason::ValueMissing);
+    }
+
+    return None;
+}
+
+// Radios are grouped by name inside of their form, or their document when they aren't in one.
+fn get_radio_scope(
+    entity: Entity,
+    query_parent: &Query<&Parent>,
+    query_inline: &Query<&HydaInlineIn>,
+    query_form: &Query<&HydaForm>,
+    query_document: &Query<&HydaDocument>,
+) -> Option<Entity> {
+    return get_form(entity, query_parent, query_form).or_else(|| get_document_root(entity, query_parent, query_inline, query_document));
+}
+
+// The names of the checked radio groups, along with the scope of each one (from "get_scope").
+fn get_checked_radio_groups(query_checkable: &Query<(Entity, &HydaCheckable)>, get_scope: impl Fn(Entity) -> Option<Entity>) -> Vec<(Option<Entity>, String)> {
+    return query_checkable.iter()
+        .filter(|(_, c)| c.kind() == HydaCheckableKind::Radio && c.is_checked())
+        .filter_map(|(e, c)| c.name().map(|n| (get_scope(e), n.to_string())))
+        .collect();
+}
+
+fn get_invalid_reason(
+    entity: Entity,
+    radio_scope: Option<Entity>,
+    checked_radio_groups: &Vec<(Option<Entity>, String)>,
+    query_input: &Query<(Entity, &HydaInput)>,
+    query_checkable: &Query<(Entity, &HydaCheckable)>,
+    query_select: &Query<(Entity, &HydaSelect)>,
+) -> Option<HydaInvalidReason> {
+
+    if let Ok((_, input)) = query_input.get(entity) {
+        return validate_input(input);
+    }
+
+    if let Ok((_, checkable)) = query_checkable.get(entity) {
+        return validate_checkable(checkable, radio_scope, checked_radio_groups);
+    }
+
+    if let Ok((_, select)) = query_select.get(entity) {
+        return validate_select(select);
+    }
+
+    return None;
+}
+
+// Keeps HydaInvalid up to date every time a field changes.
+pub(crate) fn validate_fields(
+    mut commands: Commands,
+    query_changed: Query<(), Or<(Changed<HydaInput>, Changed<HydaCheckable>, Changed<HydaSelect>)>>,
+    query_input: Query<(Entity, &HydaInput)>,
+    query_checkable: Query<(Entity, &HydaCheckable)>,
+    query_select: Query<(Entity, &HydaSelect)>,
+    query_invalid: Query<&HydaInvalid>,
+    query_parent: Query<&Parent>,
+    query_inline: Query<&HydaInlineIn>,
+    query_form: Query<&HydaForm>,
+    query_document: Query<&HydaDocument>,
+) {
+    if query_changed.is_empty() {
+        return;
+    }
+
+    let get_scope = |e: Entity| get_radio_scope(e, &query_parent, &query_inline, &query_form, &query_document);
+
+    let checked_radio_groups = get_checked_radio_groups(&query_checkable, get_scope);
+
+    let fields = query_input.iter().map(|(e, _)| e)
+        .chain(query_checkable.iter().map(|(e, _)| e))
+        .chain(query_select.iter().map(|(e, _)| e));
+
+    for entity in fields {
+        let radio_scope = if query_checkable.contains(entity) { get_scope(entity) } else { None };
+
+        let reason = get_invalid_reason(entity, radio_scope, &checked_radio_groups, &query_input, &query_checkable, &query_select);
+        let current = query_invalid.get(entity).ok().map(|i| i.reason);
+
+        if reason == current {
+            continue;
+        }
+
+        match reason {
+            Some(r) => { commands.entity(entity).insert(HydaInvalid { reason: r }); },
+            None => { commands.entity(entity).remove::<HydaInvalid>(); },
+        }
+    }
+}
+
+fn get_form(entity: Entity, query_parent: &Query<&Parent>, query_form: &Query<&HydaForm>) -> Option<Entity> {
+
+    let mut current = entity;
+
+    while let Ok(p) = query_parent.get(current) {
+        current = p.get();
+
+        if query_form.contains(current) {
+            return Some(current);
+        }
+    }
+
+    return None;
+}
+
+fn is_submit_button(clickable: &HydaClickable) -> bool {
+
+    let button_type = clickable.attributes().get("type").map(|t| t.to_lowercase());
+
+    match clickable.tag_name() {
+        // Buttons inside of forms submit them unless they say otherwise.
+        "button" => button_type.is_none() || button_type.as_deref() == Some("submit"),
+        "input" => button_type.as_deref() == Some("submit"),
+        _ => false,
+    }
+}
+
+// They don't get a component of their own (there's nothing to show), their value is just the attribute.
+fn is_hidden_input(element: &HydaElement) -> bool {
+    return element.tag == "input" && element.attribute("type").map(|t| t.to_lowercase()).as_deref() == Some("hidden");
+}
+
+fn add_field(fields: &mut HashMap<String, String>, name: &str, value: &str) {
+    match fields.get_mut(name) {
+        Some(v) => { v.push(','); v.push_str(value); },
+        None => { fields.insert(name.to_string(), value.to_string()); },
+    }
+}
+
+// Submits with a click on a submit button, or Enter on a (single line) text input.
+pub(crate) fn submit_forms(
+    mut commands: Commands,
+    mut click_events: EventReader<HydaClick>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut submit_events: EventWriter<HydaFormSubmit>,
+    query_form: Query<&HydaForm>,
+    query_clickable: Query<&HydaClickable>,
+    query_focused_input: Query<(Entity, &HydaInput), With<HydaFocused>>,
+    query_focused: Query<Entity, With<HydaFocused>>,
+    query_parent: Query<&Parent>,
+    query_children: Query<&Children>,
+    query_input: Query<(Entity, &HydaInput)>,
+    query_checkable: Query<(Entity, &HydaCheckable)>,
+    query_select: Query<(Entity, &HydaSelect)>,
+    query_range: Query<&HydaRange>,
+    query_element: Query<&HydaElement>,
+) {
+    // The form, and the button that submitted it (if any).
+    let mut submissions: Vec<(Entity, Option<Entity>)> = Vec::new();
+
+    for click in click_events.read() {
+        let Ok(clickable) = query_clickable.get(click.entity) else { continue; };
+
+        if !is_submit_button(clickable) {
+            continue;
+        }
+
+        if let Some(form) = get_form(click.entity, &query_parent, &query_form) {
+            submissions.push((form, Some(click.entity)));
+        }
+    }
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed || event.logical_key != Key::Enter {
+            continue;
+        }
+
+        for (entity, input) in &query_focused_input {
+            if input.kind().is_multiline() {
+                continue;
+            }
+
+            if let Some(form) = get_form(entity, &query_parent, &query_form) {
+                submissions.push((form, None));
+            }
+        }
+    }
+
+    for (form_entity, submitter) in submissions {
+        let form = query_form.get(form_entity).unwrap();
+
+        let submitter_attributes = submitter.and_then(|s| query_clickable.get(s).ok()).map(|c| c.attributes().clone()).unwrap_or_default();
+
+        let must_validate = form.is_validated() && !submitter_attributes.contains_key("formnovalidate");
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut first_invalid: Option<Entity> = None;
+
+        // Only the radios of this form count for its groups.
+        let checked_radio_groups = get_checked_radio_groups(&query_checkable, |e| get_form(e, &query_parent, &query_form))
+            .into_iter()
+            .filter(|(s, _)| *s == Some(form_entity))
+            .collect();
+
+        for entity in query_children.iter_descendants(form_entity) {
+            if must_validate && first_invalid.is_none() {
+                if get_invalid_reason(entity, Some(form_entity), &checked_radio_groups, &query_input, &query_checkable, &query_select).is_some() {
+                    first_invalid = Some(entity);
+                }
+            }
+
+            if let Ok((_, input)) = query_input.get(entity) {
+                if let (Some(name), false) = (input.attributes().get("name"), input.attributes().contains_key("disabled")) {
+                    add_field(&mut fields, name, input.value());
+                }
+            }
+
+            if let Ok((_, checkable)) = query_checkable.get(entity) {
+                if let (Some(name), true, false) = (checkable.name(), checkable.is_checked(), checkable.is_disabled()) {
+                    add_field(&mut fields, name, checkable.value());
+                }
+            }
+
+            if let Ok((_, select)) = query_select.get(entity) {
+                if let (Some(name), false) = (select.attributes().get("name"), select.is_disabled()) {
+                    for v in select.values() {
+                        add_field(&mut fields, name, &v);
+                    }
+                }
+            }
+
+            if let Ok(range) = query_range.get(entity) {
+                if let (Some(name), false) = (range.attributes().get("name"), range.is_disabled()) {
+                    add_field(&mut fields, name, &range.value().to_string());
+                }
+            }
+
+            if let Some(element) = query_element.get(entity).ok().filter(|e| is_hidden_input(e)) {
+                if let (Some(name), None) = (element.attribute("name"), element.attribute("disabled")) {
+                    add_field(&mut fields, name, element.attribute("value").unwrap_or_default());
+                }
+            }
+        }
+
+        // Instead of submitting, the first invalid field gets the focus.
+        if let Some(invalid) = first_invalid {
+            for e in &query_focused {
+                if e != invalid {
+                    commands.entity(e).remove::<HydaFocused>();
+                }
+            }
+
+            commands.entity(invalid).insert(HydaFocused { visible: true });
+
+            continue;
+        }
+
+        // Same as browsers, the button that was used is part of the data.
+        if let (Some(name), Some(value)) = (submitter_attributes.get("name"), submitter_attributes.get("value")) {
+            add_field(&mut fields, name, value);
+        }
+
+        submit_events.send(HydaFormSubmit {
+            entity: form_entity,
+            form_id: form.attributes.get("id").cloned(),
+            action: submitter_attributes.get("formaction").or(form.attributes.get("action")).cloned(),
+            fields: fields,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use crate::tests::{test_app, spawn_html};
+
+    fn submit(html: &str) -> Vec<HydaFormSubmit> {
+        let mut app = test_app();
+        app.add_event::<HydaClick>();
+        app.add_event::<KeyboardInput>();
+        app.add_event::<HydaFormSubmit>();
+        app.add_systems(Update, submit_forms);
+
+        spawn_html(&mut app, html);
+
+        let world = app.world_mut();
+        let (button, clickable) = world.query::<(Entity, &HydaClickable)>().single(world);
+        let click = HydaClick::new(button, clickable);
+
+        app.world_mut().send_event(click);
+        app.update();
+
+        let events = app.world().resource::<Events<HydaFormSubmit>>();
+        return events.get_reader().read(events).cloned().collect();
+    }
+
+    // The reason each named field of "html" is invalid for.
+    fn validate(html: &str) -> HashMap<String, Option<HydaInvalidReason>> {
+        let mut app = test_app();
+        spawn_html(&mut app, html);
+
+        let world = app.world_mut();
+        let mut state: SystemState<(Query<(Entity, &HydaInput)>, Query<(Entity, &HydaCheckable)>, Query<(Entity, &HydaSelect)>, Query<&HydaElement>)> = SystemState::new(world);
+        let (query_input, query_checkable, query_select, query_element) = state.get(world);
+
+        let checked_radio_groups = get_checked_radio_groups(&query_checkable, |_| None);
+
+        return query_input.iter().map(|(e, _)| e)
+            .chain(query_checkable.iter().map(|(e, _)| e))
+            .chain(query_select.iter().map(|(e, _)| e))
+            .filter_map(|e| query_element.get(e).ok().and_then(|h| h.attribute("name")).map(|n| (e, n.to_string())))
+            .map(|(e, name)| (name, get_invalid_reason(e, None, &checked_radio_groups, &query_input, &query_checkable, &query_select)))
+            .collect();
+    }
+
+    #[test]
+    fn validate_required_fields() {
+        let reasons = validate("<form>\
+            <input name=\"empty\" required>\
+            <input name=\"filled\" value=\"Ana\" required>\
+            <input name=\"optional\">\
+            <input type=\"checkbox\" name=\"terms\" required>\
+            <input type=\"checkbox\" name=\"news\" checked required>\
+            <input type=\"radio\" name=\"class\" value=\"mage\" required><input type=\"radio\" name=\"class\" value=\"rogue\" checked>\
+            <input type=\"radio\" name=\"race\" value=\"elf\" required>\
+        </form>");
+
+        assert_eq!(reasons["empty"], Some(HydaInvalidReason::ValueMissing));
+        assert_eq!(reasons["filled"], None);
+        assert_eq!(reasons["optional"], None);
+        assert_eq!(reasons["terms"], Some(HydaInvalidReason::ValueMissing));
+        assert_eq!(reasons["news"], None);
+        assert_eq!(reasons["class"], None);
+        assert_eq!(reasons["race"], Some(HydaInvalidReason::ValueMissing));
+    }
+
+    #[test]
+    fn validate_patterns() {
+        let reasons = validate("<form>\
+            <input name=\"code\" value=\"AB12\" pattern=\"[A-Z]{2}[0-9]{2}\">\
+            <input name=\"longer\" value=\"AB123\" pattern=\"[A-Z]{2}[0-9]{2}\">\
+            <input name=\"broken\" value=\"x\" pattern=\"(\">\
+            <input name=\"empty\" pattern=\"[0-9]+\">\
+            <textarea name=\"notes\" pattern=\"[0-9]+\">abc</textarea>\
+        </form>");
+
+        assert_eq!(reasons["code"], None);
+        // The whole value has to match.
+        assert_eq!(reasons["longer"], Some(HydaInvalidReason::PatternMismatch));
+        assert_eq!(reasons["broken"], None);
+        assert_eq!(reasons["empty"], None);
+        assert_eq!(reasons["notes"], None);
+    }
+
+    #[test]
+    fn validate_min_and_max() {
+        let reasons = validate("<form>\
+            <input type=\"number\" name=\"low\" value=\"0\" min=\"1\" max=\"10\">\
+            <input type=\"number\" name=\"high\" value=\"11\" min=\"1\" max=\"10\">\
+            <input type=\"number\" name=\"edge\" value=\"10\" min=\"1\" max=\"10\">\
+            <input type=\"number\" name=\"text\" value=\"ten\">\
+            <input name=\"not_a_number\" value=\"0\" min=\"1\">\
+        </form>");
+
+        assert_eq!(reasons["low"], Some(HydaInvalidReason::RangeUnderflow));
+        assert_eq!(reasons["high"], Some(HydaInvalidReason::RangeOverflow));
+        assert_eq!(reasons["edge"], None);
+        assert_eq!(reasons["text"], Some(HydaInvalidReason::BadInput));
+        assert_eq!(reasons["not_a_number"], None);
+    }
+
+    #[test]
+    fn submit_hidden_inputs() {
+        let submits = submit("<form id=\"start\"><input type=\"hidden\" name=\"level\" value=\"3\"><input type=\"hidden\" name=\"cheat\" value=\"1\" disabled><button>Go</button></form>");
+
+        assert_eq!(submits.len(), 1);
+        assert_eq!(submits[0].form_id.as_deref(), Some("start"));
+        assert_eq!(submits[0].fields.get("level").map(|v| v.as_str()), Some("3"));
+        assert!(!submits[0].fields.contains_key("cheat"));
+    }
+}
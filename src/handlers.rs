@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemId;
+use std::collections::HashMap;
+
+use crate::HydaClick;
+
+pub enum HydaHandler {
+    // A one-shot system, registered with "World::register_system".
+    System(SystemId),
+    // Same, but the system gets the click that triggered it as "In<HydaClick>".
+    SystemWithClick(SystemId<HydaClick>),
+    // Builds an event from the click and sends it.
+    Event(Box<dyn Fn(&HydaClick, &mut Commands) + Send + Sync>),
+}
+
+// Named handlers that HTML can refer to, like <button onclick="start_game">.
+#[derive(Resource, Default)]
+pub struct HydaHandlers {
+    handlers: HashMap<String, HydaHandler>,
+}
+
+impl HydaHandlers {
+    pub fn register(&mut self, name: &str, handler: HydaHandler) {
+        self.handlers.insert(name.to_string(), handler);
+    }
+
+    pub fn register_system(&mut self, name: &str, id: SystemId) {
+        self.register(name, HydaHandler::System(id));
+    }
+
+    pub fn register_system_with_click(&mut self, name: &str, id: SystemId<HydaClick>) {
+        self.register(name, HydaHandler::SystemWithClick(id));
+    }
+
+    pub fn register_event<E: Event, F: Fn(&HydaClick) -> E + Send + Sync + 'static>(&mut self, name: &str, constructor: F) {
+        self.register(name, HydaHandler::Event(Box::new(move |click: &HydaClick, commands: &mut Commands| {
+            let event = constructor(click);
+            commands.add(move |world: &mut World| {
+                world.send_event(event);
+            });
+        })));
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<HydaHandler> {
+        return self.handlers.remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        return self.handlers.contains_key(name);
+    }
+
+    fn run(&self, name: &str, click: &HydaClick, commands: &mut Commands) -> bool {
+        match self.handlers.get(name) {
+            Some(HydaHandler::System(id)) => commands.run_system(*id),
+            Some(HydaHandler::SystemWithClick(id)) => commands.run_system_with_input(*id, click.clone()),
+            Some(HydaHandler::Event(constructor)) => constructor(click, commands),
+            None => return false,
+        }
+
+        return true;
+    }
+}
+
+pub trait HydaHandlersAppExt {
+    fn add_hyda_handler_system<M, S: IntoSystem<(), (), M> + 'static>(&mut self, name: &str, system: S) -> &mut Self;
+    fn add_hyda_handler_event<E: Event, F: Fn(&HydaClick) -> E + Send + Sync + 'static>(&mut self, name: &str, constructor: F) -> &mut Self;
+}
+
+impl HydaHandlersAppExt for App {
+    fn add_hyda_handler_system<M, S: IntoSystem<(), (), M> + 'static>(&mut self, name: &str, system: S) -> &mut Self {
+        let id = self.register_system(system);
+        self.world_mut().get_resource_or_insert_with(HydaHandlers::default).register_system(name, id);
+        self
+    }
+
+    fn add_hyda_handler_event<E: Event, F: Fn(&HydaClick) -> E + Send + Sync + 'static>(&mut self, name: &str, constructor: F) -> &mut Self {
+        self.world_mut().get_resource_or_insert_with(HydaHandlers::default).register_event(name, constructor);
+        self
+    }
+}
+
+// Handler names for an event, from "on<event>" and "data-hyda-on-<event>".
+// Accepts things like "start_game", "start_game()" and "save(); quit()".
+pub fn get_handler_names(attributes: &HashMap<String, String>, event_name: &str) -> Vec<String> {
+
+    let mut names: Vec<String> = Vec::new();
+
+    for attr in [format!("on{}", event_name), format!("data-hyda-on-{}", event_name)] {
+        if let Some(value) = attributes.get(&attr) {
+            for n in value.split(';') {
+                let final_name = n.trim().trim_end_matches("()").trim();
+
+                if !final_name.is_empty() {
+                    names.push(final_name.to_string());
+                }
+            }
+        }
+    }
+
+    return names;
+}
+
+pub(crate) fn run_click_handlers(
+    mut commands: Commands,
+    mut click_events: EventReader<HydaClick>,
+    handlers: Res<HydaHandlers>,
+) {
+    for click in click_events.read() {
+        for name in get_handler_names(&click.attributes, "click") {
+            if !handlers.run(&name, click, &mut commands) {
+                warn!("bevy_hyda: there's no handler registered as \"{}\", register it with HydaHandlers or add_hyda_handler_system()", name);
+            }
+        }
+    }
+}
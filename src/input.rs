@@ -0,0 +1,560 @@
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::window::{Ime, PrimaryWindow};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::HydaFocused;
+
+const CARET_BLINK_TIME: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HydaInputKind {
+    Text,
+    Password,
+    Number,
+    TextArea,
+}
+
+impl HydaInputKind {
+    pub fn is_multiline(&self) -> bool {
+        return *self == HydaInputKind::TextArea;
+    }
+}
+
+// Sent every time the value of an <input> or <textarea> changes because of the user.
+#[derive(Event, Debug, Clone)]
+pub struct HydaInputChanged {
+    pub entity: Entity,
+    pub element_id: Option<String>,
+    pub name: Option<String>,
+    pub value: String,
+}
+
+// Text inputs, the caret and selection are counted in chars (not bytes).
+#[derive(Component, Debug, Clone)]
+pub struct HydaInput {
+    kind: HydaInputKind,
+    attributes: HashMap<String, String>,
+    value: String,
+    placeholder: String,
+    max_length: Option<usize>,
+    // Compiled once, the value is checked against it every time it changes.
+    pattern: Option<Regex>,
+    caret: usize,
+    anchor: Option<usize>,
+    preedit: String,
+    text_entity: Entity,
+    text_style: TextStyle,
+    last_edit: f32,
+}
+
+impl HydaInput {
+    pub(crate) fn new(kind: HydaInputKind, attributes: HashMap<String, String>, value: String, text_entity: Entity, text_style: TextStyle) -> Self {
+        let mut input = Self {
+            kind: kind,
+            placeholder: attributes.get("placeholder").cloned().unwrap_or_default(),
+            max_length: attributes.get("maxlength").and_then(|m| m.trim().parse::<usize>().ok()),
+            pattern: get_pattern(kind, &attributes),
+            attributes: attributes,
+            value: String::new(),
+            caret: 0,
+            anchor: None,
+            preedit: String::new(),
+            text_entity: text_entity,
+            text_style: text_style,
+            last_edit: 0.0,
+        };
+
+        input.set_value(&value);
+
+        return input;
+    }
+
+    pub fn kind(&self) -> HydaInputKind {
+        return self.kind;
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn value(&self) -> &str {
+        return &self.value;
+    }
+
+    // Replaces the value (cut to "maxlength" if needed) and moves the caret to the end.
+    pub fn set_value(&mut self, value: &str) {
+        self.value = match self.max_length {
+            Some(m) => value.chars().take(m).collect(),
+            None => value.to_string(),
+        };
+
+        if !self.kind.is_multiline() {
+            self.value = self.value.replace('\n', " ");
+        }
+
+        self.caret = self.len();
+        self.anchor = None;
+    }
+
+    pub fn placeholder(&self) -> &str {
+        return &self.placeholder;
+    }
+
+    // Like browsers, the value isn't touched: "value" is only the initial one, and a shorter "maxlength" doesn't cut it.
+    pub(crate) fn set_attributes(&mut self, attributes: HashMap<String, String>) {
+        self.placeholder = attributes.get("placeholder").cloned().unwrap_or_default();
+        self.max_length = attributes.get("maxlength").and_then(|m| m.trim().parse::<usize>().ok());
+        self.pattern = get_pattern(self.kind, &attributes);
+        self.attributes = attributes;
+    }
+
+    pub(crate) fn pattern(&self) -> Option<&Regex> {
+        return self.pattern.as_ref();
+    }
+
+    pub fn caret(&self) -> usize {
+        return self.caret;
+    }
+
+    // Start and end of the selected chars, if there's something selected.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+
+        if anchor == self.caret {
+            return None;
+        }
+
+        return Some((anchor.min(self.caret), anchor.max(self.caret)));
+    }
+
+    pub fn selected_text(&self) -> String {
+        match self.selection() {
+            Some((start, end)) => self.value.chars().skip(start).take(end - start).collect(),
+            None => String::new(),
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.len();
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        return self.attributes.contains_key("readonly") || self.attributes.contains_key("disabled");
+    }
+
+    fn len(&self) -> usize {
+        return self.value.chars().count();
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        return self.value.char_indices().nth(char_index).map(|(i, _)| i).unwrap_or(self.value.len());
+    }
+
+    fn move_caret(&mut self, position: usize, extend_selection: bool) {
+        if extend_selection {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.caret);
+            }
+        }
+        else {
+            self.anchor = None;
+        }
+
+        self.caret = position.min(self.len());
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+
+        let (start_byte, end_byte) = (self.byte_index(start), self.byte_index(end));
+
+        self.value.replace_range(start_byte..end_byte, "");
+        self.caret = start;
+        self.anchor = None;
+
+        return true;
+    }
+
+    fn insert_str(&mut self, text: &str) -> bool {
+        let mut final_text: String = if self.kind.is_multiline() { text.to_string() } else { text.replace('\n', " ") };
+
+        if self.kind == HydaInputKind::Number {
+            final_text.retain(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E');
+        }
+
+        let deleted = self.delete_selection();
+
+        if let Some(m) = self.max_length {
+            let available = m.saturating_sub(self.len());
+            final_text = final_text.chars().take(available).collect();
+        }
+
+        if final_text.is_empty() {
+            return deleted;
+        }
+
+        let byte = self.byte_index(self.caret);
+
+        self.value.insert_str(byte, &final_text);
+        self.caret += final_text.chars().count();
+
+        return true;
+    }
+
+    fn backspace(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+
+        if self.caret == 0 {
+            return false;
+        }
+
+        let byte = self.byte_index(self.caret - 1);
+
+        self.value.remove(byte);
+        self.caret -= 1;
+
+        return true;
+    }
+
+    fn delete(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+
+        if self.caret >= self.len() {
+            return false;
+        }
+
+        let byte = self.byte_index(self.caret);
+
+        self.value.remove(byte);
+
+        return true;
+    }
+
+    // Start of the line the char at "position" is in, and its column.
+    fn line_start_and_column(&self, position: usize) -> (usize, usize) {
+        let before: Vec<char> = self.value.chars().take(position).collect();
+
+        let start = before.iter().rposition(|c| *c == '\n').map(|i| i + 1).unwrap_or(0);
+
+        return (start, position - start);
+    }
+
+    fn line_end(&self, position: usize) -> usize {
+        return self.value.chars().skip(position).position(|c| c == '\n').map(|i| position + i).unwrap_or(self.len());
+    }
+
+    fn caret_to_line_start(&self) -> usize {
+        if !self.kind.is_multiline() { 0 } else { self.line_start_and_column(self.caret).0 }
+    }
+
+    fn caret_to_line_end(&self) -> usize {
+        if !self.kind.is_multiline() { self.len() } else { self.line_end(self.caret) }
+    }
+
+    fn caret_to_previous_line(&self) -> usize {
+        let (start, column) = self.line_start_and_column(self.caret);
+
+        if start == 0 {
+            return 0;
+        }
+
+        let (previous_start, _) = self.line_start_and_column(start - 1);
+
+        return (previous_start + column).min(start - 1);
+    }
+
+    fn caret_to_next_line(&self) -> usize {
+        let (_, column) = self.line_start_and_column(self.caret);
+        let end = self.line_end(self.caret);
+
+        if end >= self.len() {
+            return self.len();
+        }
+
+        return (end + 1 + column).min(self.line_end(end + 1));
+    }
+
+    // Arrow up/down on <input type="number">, honoring "step", "min" and "max".
+    fn step_number(&mut self, direction: f64) -> bool {
+        let get_number = |attr: &str| self.attributes.get(attr).and_then(|v| v.trim().parse::<f64>().ok());
+
+        let step = get_number("step").unwrap_or(1.0);
+        let mut number = self.value.trim().parse::<f64>().unwrap_or(0.0) + step * direction;
+
+        if let Some(min) = get_number("min") { number = number.max(min); }
+        if let Some(max) = get_number("max") { number = number.min(max); }
+
+        let new_value = if number.fract() == 0.0 { format!("{}", number as i64) } else { format!("{}", number) };
+
+        if new_value == self.value {
+            return false;
+        }
+
+        self.set_value(&new_value);
+
+        return true;
+    }
+
+    // What's actually drawn, password inputs only show dots.
+    fn displayed_value(&self) -> String {
+        if self.kind == HydaInputKind::Password {
+            return "•".repeat(self.len());
+        }
+
+        return self.value.clone();
+    }
+}
+
+// The pattern has to match the whole value, and invalid patterns are ignored (like browsers do).
+// It doesn't apply to <textarea>.
+fn get_pattern(kind: HydaInputKind, attributes: &HashMap<String, String>) -> Option<Regex> {
+
+    if kind.is_multiline() {
+        return None;
+    }
+
+    return attributes.get("pattern").and_then(|p| Regex::new(&format!("^(?:{})$", p)).ok());
+}
+
+pub fn get_input_kind(tag_name: &str, attributes: &HashMap<String, String>) -> Option<HydaInputKind> {
+    match tag_name {
+        "textarea" => Some(HydaInputKind::TextArea),
+        "input" => match attributes.get("type").map(|t| t.to_lowercase()).as_deref() {
+            None | Some("text") | Some("search") | Some("email") | Some("url") | Some("tel") => Some(HydaInputKind::Text),
+            Some("password") => Some(HydaInputKind::Password),
+            Some("number") => Some(HydaInputKind::Number),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn send_input_changed(entity: Entity, input: &HydaInput, changed_events: &mut EventWriter<HydaInputChanged>) {
+    changed_events.send(HydaInputChanged {
+        entity: entity,
+        element_id: input.attributes.get("id").cloned(),
+        name: input.attributes.get("name").cloned(),
+        value: input.value.clone(),
+    });
+}
+
+pub(crate) fn focus_text_inputs(
+    mut query_inputs: Query<&mut HydaInput, Added<HydaFocused>>,
+) {
+    for mut input in &mut query_inputs {
+        let end = input.len();
+        input.move_caret(end, false);
+    }
+}
+
+// IME composition (for CJK and such) only while a text input has the focus.
+pub(crate) fn update_ime(
+    mut query_window: Query<&mut Window, With<PrimaryWindow>>,
+    query_focused: Query<(&Node, &GlobalTransform), (With<HydaInput>, With<HydaFocused>)>,
+) {
+    let Ok(mut window) = query_window.get_single_mut() else { return; };
+
+    let focused = query_focused.iter().next();
+
+    if window.ime_enabled != focused.is_some() {
+        window.ime_enabled = focused.is_some();
+    }
+
+    if let Some((node, transform)) = focused {
+        let position = Vec2::new(node.logical_rect(transform).min.x, node.logical_rect(transform).max.y);
+
+        if window.ime_position != position {
+            window.ime_position = position;
+        }
+    }
+}
+
+pub(crate) fn type_into_inputs(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut ime_events: EventReader<Ime>,
+    mut changed_events: EventWriter<HydaInputChanged>,
+    mut query_inputs: Query<(Entity, &mut HydaInput), With<HydaFocused>>,
+) {
+    let Some((entity, mut input)) = query_inputs.iter_mut().next() else {
+        keyboard_events.clear();
+        ime_events.clear();
+        return;
+    };
+
+    if input.is_read_only() {
+        keyboard_events.clear();
+        ime_events.clear();
+        return;
+    }
+
+    let mut changed = false;
+    let mut moved = false;
+    let mut commits: Vec<String> = Vec::new();
+
+    for event in ime_events.read() {
+        match event {
+            Ime::Preedit { value, .. } => {
+                input.preedit = value.clone();
+                moved = true;
+            },
+            Ime::Commit { value, .. } => {
+                input.preedit.clear();
+                changed |= input.insert_str(value);
+                commits.push(value.clone());
+            },
+            Ime::Disabled { .. } => {
+                input.preedit.clear();
+            },
+            _ => {},
+        }
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let control = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        // While composing, the keys belong to the IME.
+        if !input.preedit.is_empty() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(c) if control => {
+                if c.as_str().eq_ignore_ascii_case("a") {
+                    input.select_all();
+                    moved = true;
+                }
+            },
+            // Some platforms send both the key and the IME commit for the same text.
+            Key::Character(c) if commits.iter().any(|commit| commit.as_str() == c.as_str()) => {},
+            Key::Character(c) => changed |= input.insert_str(c),
+            Key::Space => changed |= input.insert_str(" "),
+            Key::Enter if input.kind.is_multiline() => changed |= input.insert_str("\n"),
+            Key::Backspace => changed |= input.backspace(),
+            Key::Delete => changed |= input.delete(),
+            Key::ArrowLeft => {
+                let position = match input.selection() {
+                    Some((start, _)) if !shift => start,
+                    _ => input.caret.saturating_sub(1),
+                };
+
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            Key::ArrowRight => {
+                let position = match input.selection() {
+                    Some((_, end)) if !shift => end,
+                    _ => input.caret + 1,
+                };
+
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            Key::ArrowUp if input.kind.is_multiline() => {
+                let position = input.caret_to_previous_line();
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            Key::ArrowDown if input.kind.is_multiline() => {
+                let position = input.caret_to_next_line();
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            Key::ArrowUp if input.kind == HydaInputKind::Number => changed |= input.step_number(1.0),
+            Key::ArrowDown if input.kind == HydaInputKind::Number => changed |= input.step_number(-1.0),
+            Key::Home => {
+                let position = input.caret_to_line_start();
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            Key::End => {
+                let position = input.caret_to_line_end();
+                input.move_caret(position, shift);
+                moved = true;
+            },
+            _ => {},
+        }
+    }
+
+    if changed || moved {
+        // Keeps the caret solid while typing.
+        input.last_edit = time.elapsed_seconds();
+    }
+
+    if changed {
+        send_input_changed(entity, &input, &mut changed_events);
+    }
+}
+
+// Rebuilds the text of the inputs: the value (or placeholder), the selection, the IME preedit and the caret.
+// Bevy's text sections can't have a background, so the selection is shown by recoloring it.
+pub(crate) fn render_text_inputs(
+    time: Res<Time>,
+    query_inputs: Query<(&HydaInput, Option<&HydaFocused>)>,
+    mut query_text: Query<&mut Text>,
+) {
+    for (input, focused) in &query_inputs {
+        let Ok(mut text) = query_text.get_mut(input.text_entity) else { continue; };
+
+        let style = input.text_style.clone();
+
+        let faded_style = TextStyle { color: style.color.with_alpha(style.color.alpha() * 0.5), ..style.clone() };
+        let selected_style = TextStyle { color: Color::srgb(0.2, 0.4, 0.9), ..style.clone() };
+
+        let caret_visible = focused.is_some() && ((time.elapsed_seconds() - input.last_edit) / CARET_BLINK_TIME) as i32 % 2 == 0;
+        let caret_style = TextStyle { color: if caret_visible { style.color } else { Color::NONE }, ..style.clone() };
+
+        let mut sections: Vec<TextSection> = Vec::new();
+
+        let displayed: Vec<char> = input.displayed_value().chars().collect();
+        let (selection_start, selection_end) = input.selection().unwrap_or((input.caret, input.caret));
+
+        let get_range = |start: usize, end: usize| -> String { displayed[start..end].iter().collect() };
+
+        if displayed.is_empty() && input.preedit.is_empty() {
+            if focused.is_some() {
+                sections.push(TextSection::new("|", caret_style.clone()));
+            }
+
+            sections.push(TextSection::new(input.placeholder.clone(), faded_style.clone()));
+        }
+        else {
+            sections.push(TextSection::new(get_range(0, selection_start), style.clone()));
+            sections.push(TextSection::new(get_range(selection_start, selection_end), selected_style));
+            sections.push(TextSection::new(get_range(selection_end, displayed.len()), style.clone()));
+
+            // The caret goes between the sections, at the side of the selection it's at.
+            let caret_section = if input.caret == selection_start { 1 } else { 3 };
+
+            if focused.is_some() {
+                sections.insert(caret_section, TextSection::new(input.preedit.clone(), faded_style));
+                sections.insert(caret_section + 1, TextSection::new("|", caret_style));
+            }
+        }
+
+        // Avoids re-laying out the text every frame when nothing changed.
+        let is_different = text.sections.len() != sections.len()
+            || text.sections.iter().zip(sections.iter()).any(|(a, b)| a.value != b.value || a.style.color != b.style.color);
+
+        if is_different {
+            text.sections = sections;
+        }
+    }
+}
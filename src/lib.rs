@@ -1,8 +1,8 @@
 use bevy::prelude::*;
 use bevy::asset::{embedded_asset, io::AssetSourceId, AssetPath};
-use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::ui::RelativeCursorPosition;
 
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use scraper::Node::{Document, Element, Doctype, Text};
 use ego_tree::{NodeRef, NodeId};
 use scraper::Node;
@@ -14,29 +14,94 @@ use markup5ever::interface::TreeSink;
 
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use lightningcss::stylesheet::{StyleSheet, ParserOptions};
 use lightningcss::rules::CssRuleList;
 use lightningcss::rules::CssRule;
 use lightningcss::rules::style::StyleRule;
-use lightningcss::traits::ToCss;
+use lightningcss::traits::{ToCss, Parse};
+use lightningcss::stylesheet::PrinterOptions;
+use lightningcss::selector::Component;
+use lightningcss::selector::PseudoElement;
+use lightningcss::selector::WebKitScrollbarPseudoElement;
+use lightningcss::selector::PseudoClass;
+use lightningcss::properties::custom::{CustomProperty, CustomPropertyName, TokenOrValue, Token};
 use lightningcss::properties::Property;
 use lightningcss::values::color::CssColor;
 use lightningcss::properties::font::{FontWeight, AbsoluteFontWeight};
 use lightningcss::values::length::LengthPercentage;
 use lightningcss::values::length::LengthValue;
-use lightningcss::properties::size::Size;
+use lightningcss::properties::size::{Size, MaxSize, AspectRatio, BoxSizing};
+use lightningcss::properties::border::BorderSideWidth;
 use lightningcss::properties::font::FontSize;
 use lightningcss::properties::Property::Margin;
 use lightningcss::properties::Property::Padding;
 use lightningcss::values::length::LengthPercentageOrAuto;
+use lightningcss::values::length::Length;
+use lightningcss::values::calc::Calc;
 
-use lightningcss::properties::display::{Display, DisplayPair, DisplayOutside, DisplayInside};
+use lightningcss::properties::display::{Display, DisplayKeyword, DisplayPair, DisplayOutside, DisplayInside};
+use lightningcss::properties::overflow::OverflowKeyword;
 
-#[derive(Component, Default)]
-pub struct HydaScrolling {
-    position: f32,
-}
+mod scroll;
+pub use scroll::*;
+
+mod pseudo_class;
+pub use pseudo_class::*;
+
+mod click;
+pub use click::*;
+
+mod handlers;
+pub use handlers::*;
+
+mod navigation;
+pub use navigation::*;
+
+mod links;
+pub use links::*;
+
+mod input;
+pub use input::*;
+
+mod controls;
+pub use controls::*;
+
+mod select;
+pub use select::*;
+
+mod form;
+pub use form::*;
+
+mod focus;
+pub use focus::*;
+
+mod element;
+pub use element::*;
+
+mod query;
+pub use query::*;
+
+mod dom;
+pub use dom::*;
+
+mod restyle;
+pub use restyle::*;
+
+mod binding;
+pub use binding::*;
+
+mod template;
+pub use template::*;
+
+mod condition;
+pub use condition::*;
+
+mod custom;
+pub use custom::*;
+
+mod component;
 
 #[derive(Debug, Clone)]
 pub enum HydaAST {
@@ -45,6 +110,8 @@ pub enum HydaAST {
         attributes: HashMap<String, String>,
         content: Vec<HydaAST>,
         style: BevyHydaStyle,
+        dynamic_styles: Vec<HydaDynamicStyle>,
+        source: Option<HydaElementSource>,
     },
     HMetaElement {
         tag_name: String,
@@ -58,10 +125,35 @@ pub enum HydaAST {
     HText {
         text: String,
     },
+    HDocument {
+        url: String,
+        root: Box<HydaAST>,
+        html: HydaHtml,
+        styles: Vec<HydaStyleSheet>,
+    },
     HEmpty,
 }
 
-#[derive(Debug, Clone)]
+// Goes on the root entity of every spawned document.
+#[derive(Component, Debug, Clone)]
+pub struct HydaDocument {
+    url: String,
+    html: HydaHtml,
+    // Kept to restyle elements when the document changes.
+    pub(crate) styles: Vec<HydaStyleSheet>,
+}
+
+impl HydaDocument {
+    pub fn url(&self) -> &str {
+        return &self.url;
+    }
+
+    pub fn html(&self) -> &HydaHtml {
+        return &self.html;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct BevyHydaStyle {
     color: Option<bevy::color::Color>,
     background_color: Option<bevy::color::Color>,
@@ -69,10 +161,23 @@ pub struct BevyHydaStyle {
     font_size: Option<f32>,
     width: Option<Val>,
     height: Option<Val>,
+    min_width: Option<Val>,
+    max_width: Option<Val>,
+    min_height: Option<Val>,
+    max_height: Option<Val>,
+    aspect_ratio: Option<Option<f32>>,
+    box_sizing: Option<BoxSizing>,
     flex_direction: Option<bevy::ui::FlexDirection>,
     flex_wrap: Option<bevy::ui::FlexWrap>,
     margin: Option<UiRect>,
     padding: Option<UiRect>,
+    border: Option<UiRect>,
+    overflow_x: Option<OverflowKeyword>,
+    overflow_y: Option<OverflowKeyword>,
+    scrollbar_width: Option<f32>,
+    scrollbar_thumb_color: Option<bevy::color::Color>,
+    scrollbar_track_color: Option<bevy::color::Color>,
+    scroll_behavior_smooth: Option<bool>,
     display: Option<lightningcss::properties::display::Display>,
     justify_content: Option<bevy::ui::JustifyContent>,
     align_content: Option<bevy::ui::AlignContent>,
@@ -87,10 +192,23 @@ impl Default for BevyHydaStyle {
             font_size: None,
             width: Some(Val::Auto),
             height: Some(Val::Auto),
+            min_width: Some(Val::Auto),
+            max_width: Some(Val::Auto),
+            min_height: Some(Val::Auto),
+            max_height: Some(Val::Auto),
+            aspect_ratio: Some(None),
+            box_sizing: Some(BoxSizing::ContentBox),
             flex_direction: Some(bevy::ui::FlexDirection::Row),
             flex_wrap: Some(bevy::ui::FlexWrap::NoWrap),
             margin: Some(UiRect::all(Val::Px(0.0))),
             padding: Some(UiRect::all(Val::Px(0.0))),
+            border: Some(UiRect::all(Val::Px(0.0))),
+            overflow_x: Some(OverflowKeyword::Visible),
+            overflow_y: Some(OverflowKeyword::Visible),
+            scrollbar_width: Some(12.0),
+            scrollbar_thumb_color: Some(bevy::prelude::Color::Srgba(Srgba::rgba_u8(128, 128, 128, 200))),
+            scrollbar_track_color: Some(bevy::prelude::Color::Srgba(Srgba::rgba_u8(0, 0, 0, 25))),
+            scroll_behavior_smooth: Some(false),
             display: Some(lightningcss::properties::display::Display::Pair(
                 DisplayPair
                 {
@@ -114,6 +232,26 @@ fn css_color_to_bevy_color(col: CssColor) -> bevy::color::Color {
     }
 }
 
+fn css_token_list_to_bevy_colors(tokens: &Vec<TokenOrValue>) -> Vec<bevy::color::Color> {
+
+    let mut colors: Vec<bevy::color::Color> = Vec::new();
+
+    for t in tokens {
+        match t {
+            TokenOrValue::Color(col) => colors.push(css_color_to_bevy_color(col.clone())),
+            TokenOrValue::Token(Token::Ident(i)) => {
+                // Named colors aren't parsed inside of unknown properties.
+                if let Ok(col) = CssColor::parse_string(i.as_ref()) {
+                    colors.push(css_color_to_bevy_color(col));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    return colors;
+}
+
 fn css_font_weight_to_f32(fw: FontWeight) -> f32 {
     match fw {
         FontWeight::Absolute(afw) => {
@@ -154,12 +292,107 @@ fn css_length_percentage_or_auto_to_bevy_val(lpoa: LengthPercentageOrAuto) -> Va
     }
 }
 
+// Bevy doesn't have intrinsic sizes (yet), so min-content, max-content and fit-content
+// are approximated with Val::Auto, which already sizes the node from its content.
 fn css_size_to_bevy_val(s: Size) -> Val {
     match s {
         Size::Auto => return Val::Auto,
         Size::LengthPercentage(lp) => return css_length_percentage_to_bevy_val(lp),
-        _ => todo!(),
+        Size::MinContent(_) | Size::MaxContent(_) | Size::FitContent(_) => return Val::Auto,
+        Size::FitContentFunction(_) => return Val::Auto,
+        Size::Stretch(_) => return Val::Percent(100.0),
+        Size::Contain => return Val::Auto,
+    }
+}
+
+fn css_max_size_to_bevy_val(s: MaxSize) -> Val {
+    match s {
+        MaxSize::None => return Val::Auto,
+        MaxSize::LengthPercentage(lp) => return css_length_percentage_to_bevy_val(lp),
+        MaxSize::MinContent(_) | MaxSize::MaxContent(_) | MaxSize::FitContent(_) => return Val::Auto,
+        // fit-content(<length>) can never grow past its argument, so that's our max.
+        MaxSize::FitContentFunction(lp) => return css_length_percentage_to_bevy_val(lp),
+        MaxSize::Stretch(_) => return Val::Percent(100.0),
+        MaxSize::Contain => return Val::Auto,
+    }
+}
+
+fn css_aspect_ratio_to_f32(ar: AspectRatio) -> Option<f32> {
+    match ar.ratio {
+        Some(r) => {
+            if r.1 == 0.0 {
+                return None;
+            }
+
+            return Some(r.0 / r.1);
+        },
+        None => return None,
+    }
+}
+
+fn css_border_side_width_to_bevy_val(bsw: BorderSideWidth) -> Val {
+    match bsw {
+        BorderSideWidth::Thin => Val::Px(1.0),
+        BorderSideWidth::Medium => Val::Px(3.0),
+        BorderSideWidth::Thick => Val::Px(5.0),
+        BorderSideWidth::Length(Length::Value(lv)) => css_length_value_to_bevy_val(lv),
+        BorderSideWidth::Length(Length::Calc(c)) => match css_calc_length_to_px(&c) {
+            Some(p) => Val::Px(p),
+            None => {
+                warn!("bevy_hyda: couldn't resolve a calc() in a border width, using 0px instead");
+                Val::Px(0.0)
+            },
+        },
+    }
+}
+
+// Val can't mix units, so only the calc()s that end up being absolute lengths can be resolved.
+fn css_calc_length_to_px(calc: &Calc<Length>) -> Option<f32> {
+    match calc {
+        Calc::Value(l) => match l.as_ref() {
+            Length::Value(lv) => lv.to_px(),
+            Length::Calc(c) => css_calc_length_to_px(c),
+        },
+        Calc::Sum(a, b) => Some(css_calc_length_to_px(a)? + css_calc_length_to_px(b)?),
+        Calc::Product(n, c) => Some(n * css_calc_length_to_px(c)?),
+        _ => None,
+    }
+}
+
+// Bevy (taffy) always sizes nodes like "box-sizing: border-box", so for content-box
+// we have to add the padding and border ourselves. This only works when everything
+// is in pixels, percentages and viewport units are left as they are.
+fn apply_box_sizing(size: Val, start_padding: Val, end_padding: Val, start_border: Val, end_border: Val, box_sizing: &BoxSizing) -> Val {
+    if *box_sizing == BoxSizing::BorderBox {
+        return size;
+    }
+
+    if let Val::Px(s) = size {
+        let mut extra: f32 = 0.0;
+
+        for v in [start_padding, end_padding, start_border, end_border] {
+            if let Val::Px(p) = v {
+                extra += p;
+            }
+        }
+
+        return Val::Px(s + extra);
     }
+
+    return size;
+}
+
+fn css_overflow_keyword_to_bevy_overflow_axis(ok: &OverflowKeyword) -> bevy::ui::OverflowAxis {
+    match ok {
+        OverflowKeyword::Visible => bevy::ui::OverflowAxis::Visible,
+        OverflowKeyword::Clip => bevy::ui::OverflowAxis::Clip,
+        // Scroll containers shouldn't grow to fit their content, which is what "Hidden" does in taffy.
+        OverflowKeyword::Hidden | OverflowKeyword::Scroll | OverflowKeyword::Auto => bevy::ui::OverflowAxis::Hidden,
+    }
+}
+
+fn is_overflow_scrollable(ok: &OverflowKeyword) -> bool {
+    return *ok == OverflowKeyword::Scroll || *ok == OverflowKeyword::Auto;
 }
 
 fn css_font_size_to_f32(fos: FontSize) -> f32 {
@@ -251,10 +484,23 @@ impl BevyHydaStyle {
         let mut final_font_size: Option<f32> = None;
         let mut final_width: Option<Val> = None;
         let mut final_height: Option<Val> = None;
+        let mut final_min_width: Option<Val> = None;
+        let mut final_max_width: Option<Val> = None;
+        let mut final_min_height: Option<Val> = None;
+        let mut final_max_height: Option<Val> = None;
+        let mut final_aspect_ratio: Option<Option<f32>> = None;
+        let mut final_box_sizing: Option<BoxSizing> = None;
         let mut final_flex_direction: Option<bevy::ui::FlexDirection> = None;
         let mut final_flex_wrap: Option<bevy::ui::FlexWrap> = None;
         let mut final_margin: Option<UiRect> = None;
         let mut final_padding: Option<UiRect> = None;
+        let mut final_border: Option<UiRect> = None;
+        let mut final_overflow_x: Option<OverflowKeyword> = None;
+        let mut final_overflow_y: Option<OverflowKeyword> = None;
+        let mut final_scrollbar_width: Option<f32> = None;
+        let mut final_scrollbar_thumb_color: Option<bevy::color::Color> = None;
+        let mut final_scrollbar_track_color: Option<bevy::color::Color> = None;
+        let mut final_scroll_behavior_smooth: Option<bool> = None;
         let mut final_display: Option<lightningcss::properties::display::Display> = None;
         let mut final_justify_content: Option<bevy::ui::JustifyContent> = None;
         let mut final_align_content: Option<bevy::ui::AlignContent> = None;
@@ -267,6 +513,12 @@ impl BevyHydaStyle {
                 Property::FontSize(fos) => final_font_size = Some(css_font_size_to_f32(fos.clone())),
                 Property::Width(w) => final_width = Some(css_size_to_bevy_val(w.clone())),
                 Property::Height(h) => final_height = Some(css_size_to_bevy_val(h.clone())),
+                Property::MinWidth(w) => final_min_width = Some(css_size_to_bevy_val(w.clone())),
+                Property::MaxWidth(w) => final_max_width = Some(css_max_size_to_bevy_val(w.clone())),
+                Property::MinHeight(h) => final_min_height = Some(css_size_to_bevy_val(h.clone())),
+                Property::MaxHeight(h) => final_max_height = Some(css_max_size_to_bevy_val(h.clone())),
+                Property::AspectRatio(ar) => final_aspect_ratio = Some(css_aspect_ratio_to_f32(ar.clone())),
+                Property::BoxSizing(bs, _) => final_box_sizing = Some(bs.clone()),
                 Property::FlexDirection(fd, _) => final_flex_direction = Some(css_flex_direction_to_bevy_flex_direction(fd.clone())),
                 Property::FlexWrap(fw, _) => final_flex_wrap = Some(css_flex_wrap_to_bevy_flex_wrap(fw.clone())),
                 Property::Margin(m) => { 
@@ -356,6 +608,82 @@ impl BevyHydaStyle {
                     });
                 },
 
+                Property::BorderWidth(b) => {
+                    final_border = Some(UiRect {
+                        left: css_border_side_width_to_bevy_val(b.left.clone()),
+                        right: css_border_side_width_to_bevy_val(b.right.clone()),
+                        top: css_border_side_width_to_bevy_val(b.top.clone()),
+                        bottom: css_border_side_width_to_bevy_val(b.bottom.clone()),
+                    });
+                },
+                Property::BorderTopWidth(v) => {
+                    let mut b = final_border.unwrap_or(UiRect::all(Val::Px(0.0)));
+                    b.top = css_border_side_width_to_bevy_val(v.clone());
+                    final_border = Some(b);
+                },
+                Property::BorderBottomWidth(v) => {
+                    let mut b = final_border.unwrap_or(UiRect::all(Val::Px(0.0)));
+                    b.bottom = css_border_side_width_to_bevy_val(v.clone());
+                    final_border = Some(b);
+                },
+                Property::BorderLeftWidth(v) => {
+                    let mut b = final_border.unwrap_or(UiRect::all(Val::Px(0.0)));
+                    b.left = css_border_side_width_to_bevy_val(v.clone());
+                    final_border = Some(b);
+                },
+                Property::BorderRightWidth(v) => {
+                    let mut b = final_border.unwrap_or(UiRect::all(Val::Px(0.0)));
+                    b.right = css_border_side_width_to_bevy_val(v.clone());
+                    final_border = Some(b);
+                },
+
+                Property::Overflow(o) => {
+                    final_overflow_x = Some(o.x.clone());
+                    final_overflow_y = Some(o.y.clone());
+                },
+                Property::OverflowX(o) => final_overflow_x = Some(o.clone()),
+                Property::OverflowY(o) => final_overflow_y = Some(o.clone()),
+
+                // lightningcss doesn't know about the standard scrollbar properties yet,
+                // so they come in as unknown custom properties.
+                Property::Custom(CustomProperty { name: CustomPropertyName::Unknown(n), value }) => {
+                    match n.0.as_ref() {
+                        "scrollbar-width" => {
+                            for t in &value.0 {
+                                if let TokenOrValue::Token(Token::Ident(i)) = t {
+                                    match i.as_ref() {
+                                        "thin" => final_scrollbar_width = Some(8.0),
+                                        "none" => final_scrollbar_width = Some(0.0),
+                                        _ => final_scrollbar_width = Some(12.0),
+                                    }
+                                }
+                            }
+                        },
+                        "scrollbar-color" => {
+                            let colors = css_token_list_to_bevy_colors(&value.0);
+
+                            if colors.len() >= 2 {
+                                final_scrollbar_thumb_color = Some(colors[0]);
+                                final_scrollbar_track_color = Some(colors[1]);
+                            }
+                            else {
+                                // "auto"
+                                final_scrollbar_thumb_color = BevyHydaStyle::default().scrollbar_thumb_color;
+                                final_scrollbar_track_color = BevyHydaStyle::default().scrollbar_track_color;
+                            }
+                        },
+                        "scroll-behavior" => {
+                            for t in &value.0 {
+                                if let TokenOrValue::Token(Token::Ident(i)) = t {
+                                    final_scroll_behavior_smooth = Some(i.as_ref() == "smooth");
+                                }
+                            }
+                        },
+                        _ => {},
+                    }
+                },
+                Property::Custom(_) => {},
+
                 Property::Display(d) => final_display = Some(d.clone()),
                 Property::JustifyContent(jc, _) => final_justify_content = Some(css_justify_content_to_bevy_justify_content(jc.clone())),
                 Property::AlignContent(ac, _) => final_align_content = Some(css_align_content_to_bevy_align_content(ac.clone())),
@@ -370,22 +698,157 @@ impl BevyHydaStyle {
             font_size: final_font_size,
             width: final_width,
             height: final_height,
+            min_width: final_min_width,
+            max_width: final_max_width,
+            min_height: final_min_height,
+            max_height: final_max_height,
+            aspect_ratio: final_aspect_ratio,
+            box_sizing: final_box_sizing,
             flex_direction: final_flex_direction,
             flex_wrap: final_flex_wrap,
             margin: final_margin,
             padding: final_padding,
+            border: final_border,
+            overflow_x: final_overflow_x,
+            overflow_y: final_overflow_y,
+            scrollbar_width: final_scrollbar_width,
+            scrollbar_thumb_color: final_scrollbar_thumb_color,
+            scrollbar_track_color: final_scrollbar_track_color,
+            scroll_behavior_smooth: final_scroll_behavior_smooth,
             display: final_display,
             justify_content: final_justify_content,
             align_content: final_align_content,
         }
     }
+
+    pub fn color(&self) -> Option<bevy::color::Color> {
+        return self.color;
+    }
+
+    pub fn background_color(&self) -> Option<bevy::color::Color> {
+        return self.background_color;
+    }
+
+    pub fn font_size(&self) -> Option<f32> {
+        return self.font_size;
+    }
+
+    pub fn font_weight(&self) -> Option<f32> {
+        return self.font_weight;
+    }
+
+    pub fn to_bevy_style(&self) -> Style {
+
+        let mut final_display = bevy::ui::Display::Block;
+
+        match self.display.clone().unwrap() {
+            lightningcss::properties::display::Display::Pair(p) => {
+                match p.outside {
+                    DisplayOutside::Block => final_display = bevy::ui::Display::Block,
+                    _ => {},
+                }
+
+                match p.inside {
+                    DisplayInside::Flex { .. } => final_display = bevy::ui::Display::Flex,
+                    _ => {},
+                }
+            },
+            lightningcss::properties::display::Display::Keyword(DisplayKeyword::None) => final_display = bevy::ui::Display::None,
+            lightningcss::properties::display::Display::Keyword(_) => todo!()
+        }
+
+        let padding = self.padding.unwrap();
+        let border = self.border.unwrap();
+        let box_sizing = self.box_sizing.clone().unwrap();
+
+        Style {
+            display: final_display,
+            width: apply_box_sizing(self.width.unwrap(), padding.left, padding.right, border.left, border.right, &box_sizing),
+            height: apply_box_sizing(self.height.unwrap(), padding.top, padding.bottom, border.top, border.bottom, &box_sizing),
+            min_width: apply_box_sizing(self.min_width.unwrap(), padding.left, padding.right, border.left, border.right, &box_sizing),
+            max_width: apply_box_sizing(self.max_width.unwrap(), padding.left, padding.right, border.left, border.right, &box_sizing),
+            min_height: apply_box_sizing(self.min_height.unwrap(), padding.top, padding.bottom, border.top, border.bottom, &box_sizing),
+            max_height: apply_box_sizing(self.max_height.unwrap(), padding.top, padding.bottom, border.top, border.bottom, &box_sizing),
+            aspect_ratio: self.aspect_ratio.unwrap(),
+            flex_direction: self.flex_direction.unwrap(),
+            flex_wrap: self.flex_wrap.unwrap(),
+            margin: self.margin.unwrap(),
+            padding: padding,
+            border: border,
+            justify_content: self.justify_content.unwrap(),
+            align_content: self.align_content.unwrap(),
+            overflow: self.bevy_overflow(),
+            ..default()
+        }
+    }
+
+    pub fn bevy_overflow(&self) -> bevy::ui::Overflow {
+
+        let mut x = self.overflow_x.clone().unwrap();
+        let mut y = self.overflow_y.clone().unwrap();
+
+        // Same as browsers: if only one axis is scrollable/hidden, "visible" on the other one becomes "auto".
+        if x == OverflowKeyword::Visible && y != OverflowKeyword::Visible && y != OverflowKeyword::Clip {
+            x = OverflowKeyword::Auto;
+        }
+
+        if y == OverflowKeyword::Visible && x != OverflowKeyword::Visible && x != OverflowKeyword::Clip {
+            y = OverflowKeyword::Auto;
+        }
+
+        bevy::ui::Overflow {
+            x: css_overflow_keyword_to_bevy_overflow_axis(&x),
+            y: css_overflow_keyword_to_bevy_overflow_axis(&y),
+        }
+    }
+
+    pub fn scrolls_x(&self) -> bool {
+        return is_overflow_scrollable(&self.overflow_x.clone().unwrap());
+    }
+
+    pub fn scrolls_y(&self) -> bool {
+        return is_overflow_scrollable(&self.overflow_y.clone().unwrap());
+    }
+
+    pub fn is_scroll_container(&self) -> bool {
+        return self.scrolls_x() || self.scrolls_y();
+    }
+
+    pub fn to_scroll_content_style(&self) -> Style {
+
+        let element_style = self.to_bevy_style();
+
+        Style {
+            display: element_style.display,
+            position_type: PositionType::Relative,
+            flex_direction: element_style.flex_direction,
+            flex_wrap: element_style.flex_wrap,
+            justify_content: element_style.justify_content,
+            align_content: element_style.align_content,
+            min_width: Val::Percent(100.0),
+            min_height: Val::Percent(100.0),
+            flex_shrink: 0.0,
+            ..default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HydaScrollbarPart {
+    Scrollbar,
+    Track,
+    Thumb,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct HydaStyleSheet {
     selector: String,
+    parsed_selector: Option<Selector>,
+    scope: Option<String>,
     node_ids: Vec<NodeId>,
     bevy_style: BevyHydaStyle,
+    scrollbar_part: Option<HydaScrollbarPart>,
+    pseudo_classes: Vec<HydaPseudoClass>,
 }
 
 macro_rules! add_if_not_none {
@@ -404,34 +867,93 @@ macro_rules! add_clone_if_not_none {
     }
 }
 
-fn compose_final_style(styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyle, id: NodeId) -> BevyHydaStyle {
+// ::-webkit-scrollbar rules are matched against the element that owns the scrollbar,
+// so their properties end up in the scrollbar fields of that element's style.
+fn compose_scrollbar_style(get_style: &mut BevyHydaStyle, part_style: &BevyHydaStyle, part: HydaScrollbarPart) {
+    match part {
+        HydaScrollbarPart::Scrollbar => {
+            if let Some(Val::Px(w)) = part_style.width {
+                get_style.scrollbar_width = Some(w);
+            }
+            else if let Some(Val::Px(h)) = part_style.height {
+                get_style.scrollbar_width = Some(h);
+            }
+
+            if part_style.background_color != None {
+                get_style.scrollbar_track_color = part_style.background_color;
+            }
+        },
+        HydaScrollbarPart::Track => {
+            if part_style.background_color != None {
+                get_style.scrollbar_track_color = part_style.background_color;
+            }
+        },
+        HydaScrollbarPart::Thumb => {
+            if part_style.background_color != None {
+                get_style.scrollbar_thumb_color = part_style.background_color;
+            }
+        },
+    }
+}
+
+pub(crate) fn add_style(get_style: &mut BevyHydaStyle, s: &BevyHydaStyle) {
+    add_if_not_none!(get_style, s, color);
+    add_if_not_none!(get_style, s, background_color);
+    add_if_not_none!(get_style, s, font_weight);
+    add_if_not_none!(get_style, s, font_size);
+    add_if_not_none!(get_style, s, width);
+    add_if_not_none!(get_style, s, height);
+    add_if_not_none!(get_style, s, min_width);
+    add_if_not_none!(get_style, s, max_width);
+    add_if_not_none!(get_style, s, min_height);
+    add_if_not_none!(get_style, s, max_height);
+    add_if_not_none!(get_style, s, aspect_ratio);
+    add_clone_if_not_none!(get_style, s, box_sizing);
+    add_if_not_none!(get_style, s, flex_direction);
+    add_if_not_none!(get_style, s, flex_wrap);
+    add_if_not_none!(get_style, s, margin);
+    add_if_not_none!(get_style, s, padding);
+    add_if_not_none!(get_style, s, border);
+    add_clone_if_not_none!(get_style, s, overflow_x);
+    add_clone_if_not_none!(get_style, s, overflow_y);
+    add_if_not_none!(get_style, s, scrollbar_width);
+    add_if_not_none!(get_style, s, scrollbar_thumb_color);
+    add_if_not_none!(get_style, s, scrollbar_track_color);
+    add_if_not_none!(get_style, s, scroll_behavior_smooth);
+
+    add_clone_if_not_none!(get_style, s, display);
+
+    add_if_not_none!(get_style, s, justify_content);
+    add_if_not_none!(get_style, s, align_content);
+}
+
+pub(crate) fn compose_final_style(styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyle, id: NodeId) -> BevyHydaStyle {
 
     let mut get_style: BevyHydaStyle = BevyHydaStyle::default();
 
     add_if_not_none!(get_style, parent_style, color);
     add_if_not_none!(get_style, parent_style, font_weight);
     add_if_not_none!(get_style, parent_style, font_size);
+    add_if_not_none!(get_style, parent_style, scrollbar_thumb_color);
+    add_if_not_none!(get_style, parent_style, scrollbar_track_color);
 
     for s in styles {
+
+        // These ones depend on the state of the element, they're applied at runtime.
+        if !s.pseudo_classes.is_empty() {
+            continue;
+        }
+
         for nid in &s.node_ids {
             if *nid == id {
                 //dbg!(&s.bevy_style, &id);
 
-                add_if_not_none!(get_style, s.bevy_style, color);
-                add_if_not_none!(get_style, s.bevy_style, background_color);
-                add_if_not_none!(get_style, s.bevy_style, font_weight);
-                add_if_not_none!(get_style, s.bevy_style, font_size);
-                add_if_not_none!(get_style, s.bevy_style, width);
-                add_if_not_none!(get_style, s.bevy_style, height);
-                add_if_not_none!(get_style, s.bevy_style, flex_direction);
-                add_if_not_none!(get_style, s.bevy_style, flex_wrap);
-                add_if_not_none!(get_style, s.bevy_style, margin);
-                add_if_not_none!(get_style, s.bevy_style, padding);
-
-                add_clone_if_not_none!(get_style, s.bevy_style, display);
-
-                add_if_not_none!(get_style, s.bevy_style, justify_content);
-                add_if_not_none!(get_style, s.bevy_style, align_content);
+                if let Some(part) = s.scrollbar_part {
+                    compose_scrollbar_style(&mut get_style, &s.bevy_style, part);
+                    continue;
+                }
+
+                add_style(&mut get_style, &s.bevy_style);
             }
         }
     }
@@ -439,19 +961,53 @@ fn compose_final_style(styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyl
     return get_style;
 }
 
-fn parse_scraper_node(node: NodeRef<'_, Node>, styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyle) -> HydaAST {
+// The node holding the whole document fills whatever it's in: the window (or the viewport of its camera),
+// or the UI node it was mounted into with spawn_ui_into().
+pub(crate) fn compose_document_style(styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyle, id: NodeId) -> BevyHydaStyle {
+
+    let mut get_style = compose_final_style(styles, parent_style, id);
+
+    get_style.width = Some(Val::Percent(100.0));
+    get_style.height = Some(Val::Percent(100.0));
+
+    return get_style;
+}
+
+pub(crate) fn get_dynamic_styles(styles: &Vec<HydaStyleSheet>, id: NodeId) -> Vec<HydaDynamicStyle> {
+
+    let mut dynamic_styles: Vec<HydaDynamicStyle> = Vec::new();
+
+    for s in styles {
+        if s.pseudo_classes.is_empty() {
+            continue;
+        }
+
+        if s.node_ids.contains(&id) {
+            dynamic_styles.push(HydaDynamicStyle {
+                pseudo_classes: s.pseudo_classes.clone(),
+                style: s.bevy_style.clone(),
+            });
+        }
+    }
+
+    return dynamic_styles;
+}
+
+pub(crate) fn parse_scraper_node(node: NodeRef<'_, Node>, styles: &Vec<HydaStyleSheet>, parent_style: &BevyHydaStyle, html: &HydaHtml) -> HydaAST {
 
     match node.value() {
         Document => {
             let mut child_vec: Vec<HydaAST> = Vec::new();
 
-            let get_style = compose_final_style(styles, parent_style, node.id());
+            let get_style = compose_document_style(styles, parent_style, node.id());
 
             for c in node.children() {
-                child_vec.push(parse_scraper_node(c, styles, &get_style));
+                child_vec.push(parse_scraper_node(c, styles, &get_style, html));
             }
 
-            return HydaAST::HElement { tag_name: "html".to_string(), attributes: HashMap::new(), content: child_vec, style: get_style };
+            let source = HydaElementSource::new(html.clone(), node.id());
+
+            return HydaAST::HElement { tag_name: "html".to_string(), attributes: HashMap::new(), content: child_vec, style: get_style, dynamic_styles: Vec::new(), source: Some(source) };
         },
         Element(e) => {
 
@@ -464,7 +1020,12 @@ fn parse_scraper_node(node: NodeRef<'_, Node>, styles: &Vec<HydaStyleSheet>, par
             let get_style = compose_final_style(styles, parent_style, node.id());
 
             for c in node.children() {
-                child_vec.push(parse_scraper_node(c, styles, &get_style));
+                child_vec.push(parse_scraper_node(c, styles, &get_style, html));
+            }
+
+            // Same as writing "{{key}}" as its content.
+            if let Some(key) = e.attr("data-bind-text") {
+                child_vec = vec![HydaAST::HText { text: format!("{{{{{}}}}}", key) }];
             }
 
             let mut attrs_hashmap: HashMap<String, String> = HashMap::new();
@@ -482,7 +1043,11 @@ fn parse_scraper_node(node: NodeRef<'_, Node>, styles: &Vec<HydaStyleSheet>, par
                 return HydaAST::HMetaElement { tag_name: tag_name.clone(), attributes: attrs_hashmap.clone(), content: child_vec, style: get_style };
             }
 
-            return HydaAST::HElement { tag_name: tag_name.clone(), attributes: attrs_hashmap.clone(), content: child_vec, style: get_style };
+            let dynamic_styles = get_dynamic_styles(styles, node.id());
+
+            let source = HydaElementSource::new(html.clone(), node.id());
+
+            return HydaAST::HElement { tag_name: tag_name.clone(), attributes: attrs_hashmap.clone(), content: child_vec, style: get_style, dynamic_styles: dynamic_styles, source: Some(source) };
         },
         Doctype(d) => {
             return HydaAST::HDoctype { info: "html".to_string() };
@@ -505,7 +1070,15 @@ fn is_tag_inlined_text(tag: &str) -> bool {
     return tag == "a" || tag == "b" || tag == "i" || tag == "strong" || tag == "abbr" || tag == "span";
 }
 
-fn get_default_firasans(weight: f32) -> String {
+// Clickable elements need their own node (and Interaction), so they can't be merged into the text around them.
+fn is_element_inlined(tag: &str, attributes: &HashMap<String, String>) -> bool {
+    // Same for conditional ones, they're shown and hidden as a whole.
+    let is_conditional = attributes.contains_key("data-if") || attributes.contains_key("data-show");
+
+    return is_tag_inlined_text(tag) && !is_element_clickable(tag, attributes) && !is_conditional;
+}
+
+pub(crate) fn get_default_firasans(weight: f32) -> String {
 
     let mut final_path: String = "embedded://bevy_hyda/fonts/FiraSans-Regular.ttf".to_string();
 
@@ -532,82 +1105,216 @@ fn get_default_firasans(weight: f32) -> String {
 }
 
 impl HydaAST {
+    fn is_inlined(&self) -> bool {
+        match self {
+            HydaAST::HElement { tag_name, attributes, .. } => is_element_inlined(tag_name, attributes),
+            _ => true,
+        }
+    }
+
     pub fn spawn_ui(&self, commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity {
-        return self.spawn_ui_impl(commands, asset_server, &BevyHydaStyle::default(), &mut Vec::new()).0;
+        return self.spawn_ui_impl(commands, asset_server, Entity::PLACEHOLDER, &BevyHydaStyle::default(), &mut Vec::new()).0;
+    }
+
+    // Mounts the document under "parent" (an existing UI node), it fills that node instead of the window.
+    pub fn spawn_ui_into(&self, commands: &mut Commands, asset_server: &Res<AssetServer>, parent: Entity) -> Entity {
+        let root = self.spawn_ui(commands, asset_server);
+
+        commands.entity(parent).add_child(root);
+
+        return root;
     }
 
-    pub fn spawn_ui_impl(&self, commands: &mut Commands, asset_server: &Res<AssetServer>, parent_style: &BevyHydaStyle, text_section_vector: &mut Vec<TextSection>) -> (Entity, bool) {
+    // Spawns the document as a root rendered by "camera", for split-screen or when there's more than one camera.
+    pub fn spawn_ui_with_camera(&self, commands: &mut Commands, asset_server: &Res<AssetServer>, camera: Entity) -> Entity {
+        let root = self.spawn_ui(commands, asset_server);
+
+        commands.entity(root).insert(TargetCamera(camera));
+
+        return root;
+    }
+
+    // "parent_element" is the entity of the element this node is in, text sections are kept along with it.
+    pub fn spawn_ui_impl(&self, commands: &mut Commands, asset_server: &Res<AssetServer>, parent_element: Entity, parent_style: &BevyHydaStyle, text_section_vector: &mut Vec<(TextSection, Entity)>) -> (Entity, bool) {
         match self {
-            HydaAST::HElement { tag_name, content, style, .. } => {
+            HydaAST::HElement { tag_name, attributes, content, style, dynamic_styles, source } => {
+
+                // Until its condition is true, there's only a hidden node holding its place.
+                if let (Some(condition), Some(s)) = (attributes.get("data-if"), source) {
+                    let marker = commands.spawn((
+                        NodeBundle { style: Style { display: bevy::ui::Display::None, ..default() }, ..default() },
+                        HydaConditional::new(condition, s.clone()),
+                    )).id();
+
+                    return (marker, false);
+                }
+
+                let is_inlined = is_element_inlined(&tag_name, &attributes);
+
+                let result = commands.spawn((
+                    HydaElement::new(&tag_name, &attributes).with_source(source.clone()),
+                    HydaComputedStyle::new(style.clone()),
+                )).id();
+
+                // Text inputs draw their own value, their content (on <textarea>) is just the initial value.
+                // Same for <select>, the options are only shown in its list.
+                let input_kind = get_input_kind(&tag_name, &attributes);
+                // And <template>, its content is only spawned for the items of its list.
+                let draws_own_content = input_kind.is_some() || tag_name == "select" || tag_name == "template";
 
                 let mut child_vec: Vec<Entity> = Vec::new();
 
-                for c in content {
-                    let final_c = c.spawn_ui_impl(commands, asset_server, &style, text_section_vector);
+                for c in content.iter().filter(|_| !draws_own_content) {
+
+                    // Any text before a block (or clickable) child goes in its own text node,
+                    // otherwise it would end up inside of that child.
+                    if !c.is_inlined() && text_section_vector.len() != 0 {
+                        child_vec.push(spawn_text_node(commands, result, text_section_vector));
+                    }
+
+                    let final_c = c.spawn_ui_impl(commands, asset_server, result, &style, text_section_vector);
 
                     if !final_c.1 {
                         child_vec.push(final_c.0);
                     }
                 }
 
-                if !is_tag_inlined_text(&tag_name) {
+                if !is_inlined {
                     if text_section_vector.len() != 0 {
-                        child_vec.push(commands.spawn(TextBundle::from_sections(text_section_vector.clone())).id());
-                        text_section_vector.clear();
+                        child_vec.push(spawn_text_node(commands, result, text_section_vector));
                     }
                 }
 
-                let mut final_display = bevy::ui::Display::Block;
+                // Inline elements only add text sections, their entity just keeps the HydaElement.
+                let is_empty: bool = is_inlined;
 
-                match style.display.clone().unwrap() {
-                    lightningcss::properties::display::Display::Pair(p) => {
-                        match p.outside {
-                            DisplayOutside::Block => final_display = bevy::ui::Display::Block,
-                            _ => {},
-                        }
+                if !is_empty {
+                    commands.entity(result).insert(
+                        NodeBundle {
+                            style: style.to_bevy_style(),
+                            background_color: bevy::prelude::BackgroundColor(style.background_color.unwrap()),
+                            ..default()
+                    });
+                }
 
-                        match p.inside {
-                            DisplayInside::Flex { .. } => final_display = bevy::ui::Display::Flex,
-                            _ => {},
-                        }
-                    },
-                    lightningcss::properties::display::Display::Keyword(_) => todo!()
+                if let Some(id) = attributes.get("id") {
+                    commands.entity(result).insert(Name::new(id.clone()));
+                }
+
+                if is_empty {
+                    commands.entity(result).insert(HydaInlineIn(parent_element));
+                    commands.add(move |world: &mut World| element::add_inline_element(world, parent_element, result));
+                }
+
+                if !is_empty && is_element_clickable(&tag_name, &attributes) {
+                    commands.entity(result).insert((
+                        Button,
+                        Interaction::default(),
+                        HydaClickable::new(tag_name.clone(), attributes.clone()),
+                    ));
+                }
+
+                if let (false, Some(focusable)) = (is_empty, get_focusable(&tag_name, &attributes)) {
+                    commands.entity(result).insert((focusable, Interaction::default()));
+                }
+
+                if let (false, Some(kind)) = (is_empty, input_kind) {
+                    let text_style = TextStyle {
+                        font: asset_server.load(get_default_firasans(style.font_weight.unwrap())),
+                        font_size: style.font_size.unwrap(),
+                        color: style.color.unwrap(),
+                    };
+
+                    let value = if kind.is_multiline() { get_text_content(content) } else { attributes.get("value").cloned().unwrap_or_default() };
+
+                    let text = commands.spawn(TextBundle::default()).id();
+
+                    commands.entity(result).insert((
+                        Interaction::default(),
+                        HydaInput::new(kind, attributes.clone(), value, text, text_style),
+                    ));
+
+                    child_vec.push(text);
+                }
+
+                if let (false, Some(kind)) = (is_empty, get_checkable_kind(&tag_name, &attributes)) {
+                    spawn_checkable(commands, result, kind, &attributes, &style);
+                }
+
+                if !is_empty && is_range_input(&tag_name, &attributes) {
+                    commands.entity(result).insert(Interaction::default());
+                    spawn_range(commands, result, &attributes, &style);
+                }
+
+                if !is_empty && tag_name == "select" {
+                    let text_style = TextStyle {
+                        font: asset_server.load(get_default_firasans(style.font_weight.unwrap())),
+                        font_size: style.font_size.unwrap(),
+                        color: style.color.unwrap(),
+                    };
+
+                    let group_text_style = TextStyle { font: asset_server.load(get_default_firasans(700.0)), ..text_style.clone() };
+
+                    spawn_select(commands, result, &attributes, content, text_style, group_text_style);
+                }
+
+                if let (false, Some(condition)) = (is_empty, attributes.get("data-show")) {
+                    commands.entity(result).insert(HydaShow::new(condition));
                 }
 
-                let mut is_empty: bool = false;
-                let mut result = if !is_tag_inlined_text(&tag_name) {
-                    commands.spawn(
+                if let (false, "template", Some(data_for)) = (is_empty, tag_name.as_str(), attributes.get("data-for")) {
+                    match HydaTemplate::parse(data_for) {
+                        Some(t) => { commands.entity(result).insert(t); },
+                        None => warn!("bevy_hyda: invalid data-for \"{}\", it should look like \"item in list\"", data_for),
+                    }
+                }
+
+                if !is_empty && tag_name == "form" {
+                    commands.entity(result).insert(HydaForm::new(attributes.clone()));
+                }
+
+                if !is_empty && !dynamic_styles.is_empty() {
+                    commands.entity(result).insert((
+                        Interaction::default(),
+                        HydaStyleStates::new(style.clone(), dynamic_styles.clone()),
+                    ));
+                }
+
+                // Scroll containers get an extra node wrapping all the children, that's the one
+                // being moved around when scrolling (the element itself stays in place and clips it).
+                if !is_empty && style.is_scroll_container() {
+                    commands.entity(result).insert(RelativeCursorPosition::default());
+
+                    let content = commands.spawn((
                         NodeBundle {
-                            style: Style {
-                                display: final_display,
-                                width: style.width.unwrap(),
-                                height: style.height.unwrap(),
-                                flex_direction: style.flex_direction.unwrap(),
-                                flex_wrap: style.flex_wrap.unwrap(),
-                                margin: style.margin.unwrap(),
-                                padding: style.padding.unwrap(),
-                                justify_content: style.justify_content.unwrap(),
-                                align_content: style.align_content.unwrap(),
-                                ..default()
-                            },
-                            background_color: bevy::prelude::BackgroundColor(style.background_color.unwrap()),
+                            style: style.to_scroll_content_style(),
                             ..default()
-                    })
+                        },
+                        HydaScrolling::new(style.scrolls_x(), style.scrolls_y(), style.scroll_behavior_smooth.unwrap()),
+                    )).id();
+
+                    for c in child_vec {
+                        commands.entity(content).add_child(c);
+                    }
+
+                    commands.entity(result).add_child(content);
+
+                    spawn_scrollbars(commands, result, content, &style);
                 }
                 else {
-                    is_empty = true;
-                    commands.spawn_empty()
-                };
-
-                if tag_name == "body" {
-                    result.insert(HydaScrolling::default());
+                    for c in child_vec {
+                        commands.entity(result).add_child(c);
+                    }
                 }
 
-                for c in child_vec {
-                    result.add_child(c);
-                }
+                return (result, is_empty);
+            },
+            HydaAST::HDocument { url, root, html, styles } => {
+                let final_root = root.spawn_ui_impl(commands, asset_server, parent_element, parent_style, text_section_vector);
+
+                commands.entity(final_root.0).insert(HydaDocument { url: url.clone(), html: html.clone(), styles: styles.clone() });
 
-                return (result.id(), is_empty);
+                return final_root;
             },
             HydaAST::HText { text } => {
                 let new_text = TextSection::new(
@@ -621,7 +1328,7 @@ impl HydaAST {
                     }
                 );
 
-                text_section_vector.push(new_text);
+                text_section_vector.push((new_text, parent_element));
 
                 return (commands.spawn_empty().id(), true);
             },
@@ -630,6 +1337,21 @@ impl HydaAST {
     }
 }
 
+pub(crate) fn get_text_content(content: &Vec<HydaAST>) -> String {
+
+    let mut text = String::new();
+
+    for c in content {
+        match c {
+            HydaAST::HText { text: t } => text.push_str(t),
+            HydaAST::HElement { content: element_content, .. } => text.push_str(&get_text_content(element_content)),
+            _ => {},
+        }
+    }
+
+    return text;
+}
+
 pub fn html_string(get_str: String) -> HydaAST {
     return html_ast_impl(get_str, "".to_string());
 }
@@ -638,70 +1360,185 @@ pub fn html_file(get_url: String) -> HydaAST {
     return html_ast_impl(fs::read_to_string(&get_url).unwrap(), get_url);
 }
 
+fn get_scrollbar_part(css_selector: &lightningcss::selector::Selector) -> Option<HydaScrollbarPart> {
+    for c in css_selector.iter_raw_match_order() {
+        if let Component::PseudoElement(PseudoElement::WebKitScrollbar(sp)) = c {
+            match sp {
+                WebKitScrollbarPseudoElement::Scrollbar => return Some(HydaScrollbarPart::Scrollbar),
+                WebKitScrollbarPseudoElement::Track | WebKitScrollbarPseudoElement::TrackPiece => return Some(HydaScrollbarPart::Track),
+                WebKitScrollbarPseudoElement::Thumb => return Some(HydaScrollbarPart::Thumb),
+                _ => {},
+            }
+        }
+    }
+
+    return None;
+}
+
+fn get_dynamic_pseudo_class(component: &Component) -> Option<HydaPseudoClass> {
+    match component {
+        Component::NonTSPseudoClass(PseudoClass::Hover) => Some(HydaPseudoClass::Hover),
+        Component::NonTSPseudoClass(PseudoClass::Active) => Some(HydaPseudoClass::Active),
+        Component::NonTSPseudoClass(PseudoClass::Focus) => Some(HydaPseudoClass::Focus),
+        Component::NonTSPseudoClass(PseudoClass::FocusVisible) => Some(HydaPseudoClass::FocusVisible),
+        Component::NonTSPseudoClass(PseudoClass::Invalid) => Some(HydaPseudoClass::Invalid),
+        _ => None,
+    }
+}
+
+// Only the pseudo-classes of the element being styled can be handled, something like
+// ".menu:hover .item" stays as it is and simply never matches.
+fn get_dynamic_pseudo_classes(css_selector: &lightningcss::selector::Selector) -> Vec<HydaPseudoClass> {
+    return css_selector.iter().filter_map(get_dynamic_pseudo_class).collect();
+}
+
+// Removes the dynamic pseudo-classes from the last compound selector, so scraper can
+// find the elements that *could* match once they're hovered, pressed, etc.
+fn strip_dynamic_pseudo_classes(css_selector: &lightningcss::selector::Selector) -> String {
+
+    // The compound selectors go from right to left in match order, but each one keeps its parse order inside.
+    let mut compounds: Vec<Vec<Component>> = vec![Vec::new()];
+    let mut combinators: Vec<Component> = Vec::new();
+
+    for c in css_selector.iter_raw_match_order() {
+        if c.is_combinator() {
+            compounds.push(Vec::new());
+            combinators.push(c.clone());
+        }
+        else {
+            compounds.last_mut().unwrap().push(c.clone());
+        }
+    }
+
+    compounds[0].retain(|c| get_dynamic_pseudo_class(c).is_none());
+
+    // ":hover" on its own matches anything.
+    if compounds[0].is_empty() {
+        compounds[0].push(Component::ExplicitUniversalType);
+    }
+
+    let mut components: Vec<Component> = Vec::new();
+
+    for (i, compound) in compounds.into_iter().enumerate().rev() {
+        components.extend(compound);
+
+        if i > 0 {
+            components.push(combinators[i - 1].clone());
+        }
+    }
+
+    return lightningcss::selector::Selector::from(components).to_css_string(PrinterOptions::default()).unwrap();
+}
+
 fn add_stylesheet(stylesheet_cont: String, styles: &mut Vec<HydaStyleSheet>, document: &Html) {
+    add_scoped_stylesheet(stylesheet_cont, styles, document, None);
+}
+
+// Rules with a scope only match the elements that came from the component with that name.
+pub(crate) fn is_in_scope(element: &ElementRef, scope: &Option<String>) -> bool {
+    match scope {
+        Some(s) => element.value().attr(component::HYDA_SCOPE_ATTRIBUTE) == Some(s.as_str()),
+        None => true,
+    }
+}
+
+fn add_scoped_stylesheet(stylesheet_cont: String, styles: &mut Vec<HydaStyleSheet>, document: &Html, scope: Option<String>) {
 
-    let mut stylesheet = StyleSheet::parse(
+    let stylesheet = StyleSheet::parse(
         &stylesheet_cont, 
         ParserOptions::default()).unwrap();
 
     //dbg!(&stylesheet);
 
-    let mut final_name: String = String::new();
-    let mut final_nodeid_vec: Vec<NodeId> = Vec::new();
-
-    let mut bhs = BevyHydaStyle::default();
-
     if let CssRuleList(ref c) = stylesheet.rules {
         for style in c {
 
-            final_name = "".to_string();
-            final_nodeid_vec.clear();
-            bhs = BevyHydaStyle::default();
-
             if let CssRule::Style(StyleRule { selectors, declarations, .. }) = style {
 
-                let mut selector_count: i32 = 0;
+                let bhs = BevyHydaStyle::from_lcss(&declarations.declarations, &declarations.important_declarations);
+
                 for css_selector in &selectors.0 {
-                    final_name += &format!("{:?}", css_selector.iter());
+                    let mut final_name: String = css_selector.to_css_string(PrinterOptions::default()).unwrap();
+                    let mut final_nodeid_vec: Vec<NodeId> = Vec::new();
+
+                    // scraper doesn't know about pseudo-elements, so we match the element
+                    // that owns the scrollbar and keep track of which part is being styled.
+                    let scrollbar_part = get_scrollbar_part(css_selector);
+                    let pseudo_classes = get_dynamic_pseudo_classes(css_selector);
+
+                    if !pseudo_classes.is_empty() {
+                        // Things like "::-webkit-scrollbar-thumb:hover" aren't supported (yet).
+                        if scrollbar_part.is_some() {
+                            continue;
+                        }
 
-                    if selector_count < (selectors.0.len() - 1).try_into().unwrap() {
-                        final_name += ", ";
-                        selector_count += 1;
+                        final_name = strip_dynamic_pseudo_classes(css_selector);
                     }
-                }
 
-                bhs = BevyHydaStyle::from_lcss(&declarations.declarations, &declarations.important_declarations);
-            }
+                    if scrollbar_part.is_some() {
+                        if let Some(i) = final_name.find("::") {
+                            final_name.truncate(i);
+                        }
 
-            let selector_test = Selector::parse(&final_name).unwrap();
-            for e2 in document.select(&selector_test) {
-                final_nodeid_vec.push(e2.id());
-            }
+                        if final_name.is_empty() || final_name.ends_with(' ') {
+                            final_name += "*";
+                        }
+                    }
 
-            styles.push(HydaStyleSheet {
-                selector: final_name.clone(),
-                node_ids: final_nodeid_vec.clone(),
-                bevy_style: bhs.clone(),
-            });
+                    // Kept parsed, so only the elements around a change have to be matched again later.
+                    let parsed_selector = Selector::parse(&final_name).ok();
+
+                    if let Some(selector_test) = &parsed_selector {
+                        for e2 in document.select(selector_test).filter(|e| is_in_scope(e, &scope)) {
+                            final_nodeid_vec.push(e2.id());
+                        }
+                    }
+
+                    styles.push(HydaStyleSheet {
+                        selector: final_name.clone(),
+                        parsed_selector: parsed_selector,
+                        scope: scope.clone(),
+                        node_ids: final_nodeid_vec,
+                        bevy_style: bhs.clone(),
+                        scrollbar_part: scrollbar_part,
+                        pseudo_classes: pseudo_classes,
+                    });
+                }
+            }
         }
     }
 }
 
+// Paths in a document (stylesheets, links, etc.) are relative to the document itself.
+pub(crate) fn resolve_relative_path(url: &str, href: &str) -> PathBuf {
+
+    let mut final_dir = PathBuf::from(url);
+    final_dir.pop();
+    final_dir.push(".");
+    final_dir.push(href);
+
+    return final_dir;
+}
+
 pub fn html_ast_impl(html: String, url: String) -> HydaAST {
     
     let mut document = Html::parse_document(&html);
     document.set_quirks_mode(QuirksMode::NoQuirks);
 
-    let selector = Selector::parse("link").unwrap();
+    // Components are expanded first, so the document is already flat when the styles get matched.
+    let components = component::load_components(&document, &url);
+    component::expand_components(&mut document, &components);
 
-    let mut final_dir = PathBuf::from(&url);
-    final_dir.pop();
-    final_dir.push(".");
+    let selector = Selector::parse("link").unwrap();
 
     let mut styles: Vec<HydaStyleSheet> = Vec::new();
 
     add_stylesheet(include_str!("styles/default.css").to_string(), &mut styles, &document);
 
+    for c in &components {
+        add_scoped_stylesheet(c.style().to_string(), &mut styles, &document, Some(c.name().to_string()));
+    }
+
     for element in document.select(&selector) {
 
         let mut attrs_hashmap: HashMap<String, String> = HashMap::new();
@@ -715,23 +1552,58 @@ pub fn html_ast_impl(html: String, url: String) -> HydaAST {
         if let Some(st) = attrs_hashmap.get("rel") {
             if st == "stylesheet" {
 
-                final_dir.push(final_href);
+                let final_dir = resolve_relative_path(&url, &final_href);
 
                 //dbg!(final_dir.clone().into_os_string().into_string().unwrap());
                 let stylesheet_cont = fs::read_to_string(final_dir.clone()).unwrap();
 
-                final_dir.pop();
-
                 add_stylesheet(stylesheet_cont, &mut styles, &document);
             }
         }
     }
 
-    let root = document.tree.root();
-    return parse_scraper_node(root, &styles, &BevyHydaStyle::default());
+    // After everything else, so "data-show" can hide elements whatever their display is.
+    add_stylesheet("[hidden] { display: none; }".to_string(), &mut styles, &document);
+
+    // The parsed document is kept (and shared by every element) so HydaQuery can run selectors on it later.
+    let html: HydaHtml = Arc::new(Mutex::new(document));
+
+    let root = parse_scraper_node(html.lock().unwrap().tree.root(), &styles, &BevyHydaStyle::default(), &html);
+
+    return HydaAST::HDocument { url: url, root: Box::new(root), html: html, styles: styles };
 }
 
-pub struct BevyHydaPlugin;
+#[derive(Default, Clone)]
+pub struct BevyHydaPlugin {
+    scheme_handlers: HydaSchemeHandlers,
+    custom_elements: HydaCustomElements,
+}
+
+impl BevyHydaPlugin {
+    // Runs "handler" every time a link like <a href="scheme://path?key=value"> gets clicked.
+    pub fn with_scheme_handler<F: Fn(&HydaLinkActivated, &mut Commands) + Send + Sync + 'static>(mut self, scheme: &str, handler: F) -> Self {
+        self.scheme_handlers.register(scheme, handler);
+        self
+    }
+
+    // Sends the event built by "constructor" every time a link with "scheme" gets clicked.
+    pub fn with_scheme_event<E: Event, F: Fn(&HydaLinkActivated) -> E + Send + Sync + 'static>(mut self, scheme: &str, constructor: F) -> Self {
+        self.scheme_handlers.register_event(scheme, constructor);
+        self
+    }
+
+    // Elements with this tag get the bundle returned by "spawner", which receives their attributes and computed style.
+    pub fn with_custom_element<B: Bundle, F: Fn(&HashMap<String, String>, &BevyHydaStyle) -> B + Send + Sync + 'static>(mut self, tag: &str, spawner: F) -> Self {
+        self.custom_elements.register(tag, spawner);
+        self
+    }
+
+    // Elements with this tag get the entity spawned by "spawner" as a child.
+    pub fn with_custom_element_entity<F: Fn(&HashMap<String, String>, &BevyHydaStyle, &mut World) -> Entity + Send + Sync + 'static>(mut self, tag: &str, spawner: F) -> Self {
+        self.custom_elements.register_entity(tag, spawner);
+        self
+    }
+}
 
 impl Plugin for BevyHydaPlugin {
     fn build(&self, app: &mut App) {
@@ -755,30 +1627,138 @@ impl Plugin for BevyHydaPlugin {
         embedded_asset!(app, "fonts/FiraSans-BlackItalic.ttf");
         embedded_asset!(app, "fonts/FiraSans-Black.ttf");
 
-        app.add_systems(Update, mouse_scroll);
+        app.add_event::<HydaScrollRequest>();
+        app.init_resource::<HydaGamepadScrollSettings>();
+
+        app.add_event::<HydaClick>();
+
+        app.init_resource::<HydaHandlers>();
+
+        app.init_resource::<HydaNavigation>();
+
+        app.init_resource::<HydaBindings>();
+
+        app.add_event::<HydaLinkActivated>();
+
+        app.add_event::<HydaInputChanged>();
+        app.add_event::<HydaCheckedChanged>();
+        app.add_event::<HydaRangeChanged>();
+        app.add_event::<HydaSelectChanged>();
+        app.add_event::<HydaFormSubmit>();
+        app.insert_resource(self.scheme_handlers.clone());
+        app.insert_resource(self.custom_elements.clone());
+
+        app.add_systems(Update, (
+            click::emit_clicks,
+            focus::activate_focused,
+            handlers::run_click_handlers,
+            links::emit_link_events,
+            links::run_scheme_handlers,
+            navigation::follow_links,
+            navigation::process_navigation,
+            navigation::scroll_to_pending_fragment,
+        ).chain());
+
+        app.add_systems(Update, (
+            pseudo_class::focus_on_click,
+            focus::autofocus,
+            focus::navigate_focus,
+            pseudo_class::apply_dynamic_styles,
+        ).chain());
+
+        app.add_systems(Update, (
+            input::focus_text_inputs,
+            input::update_ime,
+            input::type_into_inputs,
+            input::render_text_inputs,
+        ).chain().after(pseudo_class::focus_on_click));
+
+        app.add_systems(Update, (
+            controls::toggle_on_click,
+            controls::control_keyboard_input,
+            controls::drag_ranges,
+            controls::update_controls,
+        ).chain().after(focus::activate_focused).after(pseudo_class::focus_on_click));
+
+        app.add_systems(Update, (
+            select::select_mouse_input,
+            select::select_keyboard_input,
+            select::update_selects,
+        ).chain().after(pseudo_class::focus_on_click));
+
+        app.add_systems(Update, (
+            form::submit_forms,
+            form::validate_fields,
+        ).chain().after(focus::activate_focused).after(controls::update_controls).after(select::update_selects).before(pseudo_class::apply_dynamic_styles));
+
+        app.add_systems(Update, (
+            scroll::mouse_scroll,
+            scroll::touch_scroll,
+            scroll::gamepad_scroll,
+            scroll::apply_scroll_inertia,
+            scroll::drag_scrollbar_thumb,
+            scroll::handle_scroll_requests,
+            scroll::animate_scroll,
+            scroll::update_scrollbars,
+        ).chain());
+
+        // After Update, so changes made by commands during this frame are already there for the layout.
+        app.add_systems(PostUpdate, (
+            template::update_templates,
+            condition::update_conditions,
+            // Custom elements (registered in HydaCustomElements) add their own components once spawned.
+            custom::spawn_custom_elements,
+            restyle::restyle_elements,
+            binding::update_bound_text,
+        ).chain().before(bevy::ui::UiSystem::Layout));
     }
 }
 
-fn mouse_scroll(
-    mut mouse_wheel_events: EventReader<MouseWheel>,
-    mut query_list: Query<(&mut HydaScrolling, &mut Style, &Parent, &bevy::prelude::Node)>,
-    query_node: Query<&bevy::prelude::Node>,
-) {
-    for mouse_wheel_event in mouse_wheel_events.read() {
-        for (mut scrolling_list, mut style, parent, list_node) in &mut query_list {
-            let items_height = list_node.size().y;
-            let container_height = query_node.get(parent.get()).unwrap().size().y;
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    // Just what spawning a document needs, each test adds the systems it's testing.
+    pub(crate) fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.init_asset::<Font>();
+        return app;
+    }
+
+    pub(crate) fn spawn_html(app: &mut App, html: &str) -> Entity {
+        return spawn_ast(app, &html_string(html.to_string()));
+    }
 
-            let max_scroll = (items_height - container_height).max(0.);
+    pub(crate) fn spawn_ast(app: &mut App, ast: &HydaAST) -> Entity {
+        let mut state: SystemState<(Commands, Res<AssetServer>)> = SystemState::new(app.world_mut());
+        let (mut commands, asset_server) = state.get_mut(app.world_mut());
+        let root = ast.spawn_ui(&mut commands, &asset_server);
+        state.apply(app.world_mut());
+        return root;
+    }
 
-            let dy = match mouse_wheel_event.unit {
-                MouseScrollUnit::Line => mouse_wheel_event.y * 20.,
-                MouseScrollUnit::Pixel => mouse_wheel_event.y,
-            };
+    fn strip(selector: &str) -> String {
+        let css = format!("{} {{ color: red; }}", selector);
+        let stylesheet = StyleSheet::parse(&css, ParserOptions::default()).unwrap();
 
-            scrolling_list.position += dy;
-            scrolling_list.position = scrolling_list.position.clamp(-max_scroll, 0.);
-            style.top = Val::Px(scrolling_list.position);
-        }
+        let CssRule::Style(rule) = &stylesheet.rules.0[0] else { panic!("\"{}\" should be a style rule", selector) };
+
+        return strip_dynamic_pseudo_classes(&rule.selectors.0[0]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn strip_pseudo_classes_of_the_last_compound() {
+        assert_eq!(strip("a:hover"), "a");
+        assert_eq!(strip(".menu:hover .item:focus:active"), ".menu:hover .item");
+        assert_eq!(strip("ul > :hover"), "ul > *");
+        assert_eq!(strip(":focus-visible"), "*");
+    }
+
+    #[test]
+    fn strip_pseudo_classes_after_attribute_selectors() {
+        assert_eq!(strip("a[title=\"a > b\"]:hover"), "a[title=\"a > b\"]");
+        assert_eq!(strip("div [data-x=\"1 + 2 ~ 3\"]:invalid"), "div [data-x=\"1 + 2 ~ 3\"]");
+    }
+}
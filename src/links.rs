@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{HydaClick, HydaClickable};
+
+// Sent when a link with a scheme gets clicked, like <a href="game://start?level=3">,
+// which ends up as scheme: "game", path: "start", query: { "level": "3" }.
+#[derive(Event, Debug, Clone)]
+pub struct HydaLinkActivated {
+    pub entity: Entity,
+    pub scheme: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+pub type HydaSchemeHandler = Arc<dyn Fn(&HydaLinkActivated, &mut Commands) + Send + Sync>;
+
+// Callbacks for link schemes, filled from "BevyHydaPlugin::with_scheme_handler()".
+#[derive(Resource, Default, Clone)]
+pub struct HydaSchemeHandlers {
+    handlers: HashMap<String, Vec<HydaSchemeHandler>>,
+}
+
+impl HydaSchemeHandlers {
+    pub fn register<F: Fn(&HydaLinkActivated, &mut Commands) + Send + Sync + 'static>(&mut self, scheme: &str, handler: F) {
+        self.register_arc(scheme, Arc::new(handler));
+    }
+
+    pub(crate) fn register_arc(&mut self, scheme: &str, handler: HydaSchemeHandler) {
+        self.handlers.entry(scheme.to_lowercase()).or_default().push(handler);
+    }
+
+    pub fn register_event<E: Event, F: Fn(&HydaLinkActivated) -> E + Send + Sync + 'static>(&mut self, scheme: &str, constructor: F) {
+        self.register(scheme, move |link: &HydaLinkActivated, commands: &mut Commands| {
+            let event = constructor(link);
+            commands.add(move |world: &mut World| {
+                world.send_event(event);
+            });
+        });
+    }
+
+    pub fn remove(&mut self, scheme: &str) {
+        self.handlers.remove(&scheme.to_lowercase());
+    }
+
+    pub fn contains(&self, scheme: &str) -> bool {
+        return self.handlers.contains_key(&scheme.to_lowercase());
+    }
+}
+
+// Schemes with a meaning of their own, they're only sent as HydaLinkActivated if there's a handler for them.
+const WELL_KNOWN_SCHEMES: [&str; 12] = ["http", "https", "mailto", "tel", "ftp", "file", "data", "javascript", "about", "blob", "ws", "wss"];
+
+// Splits "scheme://path?query#fragment" (or "scheme:path?query"), returns None if there's no scheme.
+// The keys and values of the query are percent-decoded.
+pub fn parse_link(href: &str) -> Option<(String, String, HashMap<String, String>)> {
+
+    let (scheme, rest) = href.trim().split_once(':')?;
+
+    // Schemes start with a letter and only have letters, digits, '+', '-' and '.'.
+    if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') {
+        return None;
+    }
+
+    // Like "C:\saves\index.html", that's a path on Windows.
+    if scheme.len() == 1 {
+        return None;
+    }
+
+    let rest = rest.strip_prefix("//").unwrap_or(rest);
+    let rest = rest.split_once('#').map(|(r, _)| r).unwrap_or(rest);
+
+    let (path, query_string) = match rest.split_once('?') {
+        Some((p, q)) => (p, q),
+        None => (rest, ""),
+    };
+
+    let mut query: HashMap<String, String> = HashMap::new();
+
+    for pair in query_string.split('&') {
+        if pair.is_empty() {
+            continue;
+        }
+
+        match pair.split_once('=') {
+            Some((k, v)) => query.insert(decode_query_component(k), decode_query_component(v)),
+            None => query.insert(decode_query_component(pair), String::new()),
+        };
+    }
+
+    return Some((scheme.to_lowercase(), path.to_string(), query));
+}
+
+fn is_action_scheme(scheme: &str, handlers: &HydaSchemeHandlers) -> bool {
+    return handlers.contains(scheme) || !WELL_KNOWN_SCHEMES.contains(&scheme);
+}
+
+// "%20" (or "+") becomes a space, invalid escapes are kept as they are.
+fn decode_query_component(component: &str) -> String {
+
+    let bytes = component.as_bytes();
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = component.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(h)) => { decoded.push(h); i += 3; },
+            (b'+', _) => { decoded.push(b' '); i += 1; },
+            (b, _) => { decoded.push(b); i += 1; },
+        }
+    }
+
+    return String::from_utf8_lossy(&decoded).into_owned();
+}
+
+pub(crate) fn emit_link_events(
+    mut click_events: EventReader<HydaClick>,
+    mut link_events: EventWriter<HydaLinkActivated>,
+    query_clickable: Query<&HydaClickable>,
+    handlers: Res<HydaSchemeHandlers>,
+) {
+    for click in click_events.read() {
+        let Ok(clickable) = query_clickable.get(click.entity) else { continue; };
+
+        if clickable.tag_name() != "a" {
+            continue;
+        }
+
+        let Some(href) = click.attributes.get("href") else { continue; };
+        let Some((scheme, path, query)) = parse_link(href) else { continue; };
+
+        if !is_action_scheme(&scheme, &handlers) {
+            continue;
+        }
+
+        link_events.send(HydaLinkActivated {
+            entity: click.entity,
+            scheme: scheme,
+            path: path,
+            query: query,
+        });
+    }
+}
+
+pub(crate) fn run_scheme_handlers(
+    mut commands: Commands,
+    mut link_events: EventReader<HydaLinkActivated>,
+    handlers: Res<HydaSchemeHandlers>,
+) {
+    for link in link_events.read() {
+        if let Some(scheme_handlers) = handlers.handlers.get(&link.scheme) {
+            for handler in scheme_handlers {
+                handler(link, &mut commands);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_scheme_links() {
+        let (scheme, path, query) = parse_link("Game://start?level=3&hard").unwrap();
+
+        assert_eq!(scheme, "game");
+        assert_eq!(path, "start");
+        assert_eq!(query.get("level").map(|v| v.as_str()), Some("3"));
+        assert_eq!(query.get("hard").map(|v| v.as_str()), Some(""));
+
+        let (scheme, path, query) = parse_link("dialog:choice#ignored").unwrap();
+
+        assert_eq!(scheme, "dialog");
+        assert_eq!(path, "choice");
+        assert!(query.is_empty());
+    }
+
+    #[test]
+    fn decode_query_values() {
+        let (_, _, query) = parse_link("game://say?text=Hello%2C%20world&who=Old+man&bad=100%").unwrap();
+
+        assert_eq!(query.get("text").map(|v| v.as_str()), Some("Hello, world"));
+        assert_eq!(query.get("who").map(|v| v.as_str()), Some("Old man"));
+        assert_eq!(query.get("bad").map(|v| v.as_str()), Some("100%"));
+    }
+
+    #[test]
+    fn well_known_schemes_need_a_handler() {
+        let mut handlers = HydaSchemeHandlers::default();
+
+        assert!(is_action_scheme("game", &handlers));
+        assert!(!is_action_scheme("https", &handlers));
+        assert!(!is_action_scheme("mailto", &handlers));
+
+        handlers.register("https", |_, _| {});
+
+        assert!(is_action_scheme("https", &handlers));
+    }
+
+    #[test]
+    fn paths_are_not_scheme_links() {
+        assert!(parse_link("menu.html").is_none());
+        assert!(parse_link("#top").is_none());
+        assert!(parse_link("C:\\saves\\index.html").is_none());
+        assert!(parse_link("1up:start").is_none());
+    }
+}
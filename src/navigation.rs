@@ -0,0 +1,352 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{HydaAST, HydaClick, HydaClickable, HydaDocument, HydaInlineIn, HydaScrollRequest, html_file, resolve_relative_path};
+use crate::element::get_hierarchy_parent;
+
+#[derive(Debug, Clone)]
+enum HydaNavigationRequest {
+    Go { root: Entity, url: String, fragment: Option<String> },
+    Back { root: Entity },
+    Forward { root: Entity },
+}
+
+// The pages one document went through, works like the back/forward buttons of a browser.
+#[derive(Debug, Clone, Default)]
+pub struct HydaHistory {
+    entries: Vec<String>,
+    index: usize,
+}
+
+impl HydaHistory {
+    // The url of the page being shown right now.
+    pub fn current(&self) -> Option<&str> {
+        return self.entries.get(self.index).map(|u| u.as_str());
+    }
+
+    pub fn entries(&self) -> &Vec<String> {
+        return &self.entries;
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        return self.index > 0;
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        return self.index + 1 < self.entries.len();
+    }
+}
+
+// History of the pages opened by clicking links, kept for every document (by its root entity) on its own,
+// so going back in one of them doesn't touch the others.
+#[derive(Resource, Default)]
+pub struct HydaNavigation {
+    histories: HashMap<Entity, HydaHistory>,
+    pending: Vec<HydaNavigationRequest>,
+    pending_fragments: HashMap<Entity, String>,
+}
+
+impl HydaNavigation {
+    // The history of the document spawned at "root", if it navigated at least once.
+    pub fn history(&self, root: Entity) -> Option<&HydaHistory> {
+        return self.histories.get(&root);
+    }
+
+    // The url of the page being shown at "root" right now.
+    pub fn current(&self, root: Entity) -> Option<&str> {
+        return self.histories.get(&root).and_then(|h| h.current());
+    }
+
+    pub fn can_go_back(&self, root: Entity) -> bool {
+        return self.histories.get(&root).map(|h| h.can_go_back()).unwrap_or(false);
+    }
+
+    pub fn can_go_forward(&self, root: Entity) -> bool {
+        return self.histories.get(&root).map(|h| h.can_go_forward()).unwrap_or(false);
+    }
+
+    // Replaces the document spawned at "root" with the one at "url" (which can end with a #fragment).
+    pub fn navigate(&mut self, root: Entity, url: &str) {
+        let (path, fragment) = split_fragment(url);
+        self.pending.push(HydaNavigationRequest::Go { root: root, url: path.to_string(), fragment: fragment.map(|f| f.to_string()) });
+    }
+
+    pub fn back(&mut self, root: Entity) {
+        if self.can_go_back(root) {
+            self.pending.push(HydaNavigationRequest::Back { root: root });
+        }
+    }
+
+    pub fn forward(&mut self, root: Entity) {
+        if self.can_go_forward(root) {
+            self.pending.push(HydaNavigationRequest::Forward { root: root });
+        }
+    }
+}
+
+// The path and fragment of a link. Documents are files, so the query is left out ("page.html?tab=2#top" is "page.html" and "top").
+fn split_fragment(href: &str) -> (&str, Option<&str>) {
+
+    let (path, fragment) = match href.split_once('#') {
+        Some((path, fragment)) => (path, if fragment.is_empty() { None } else { Some(fragment) }),
+        None => (href, None),
+    };
+
+    return (path.split_once('?').map(|(p, _)| p).unwrap_or(path), fragment);
+}
+
+fn is_relative_html_link(path: &str) -> bool {
+
+    // Anything with a scheme (http://, mailto:, game://...) isn't a file next to the document.
+    if path.contains(':') {
+        return false;
+    }
+
+    let lower_path = path.to_lowercase();
+
+    return lower_path.ends_with(".html") || lower_path.ends_with(".htm");
+}
+
+pub(crate) fn get_document_root(entity: Entity, query_parent: &Query<&Parent>, query_inline: &Query<&HydaInlineIn>, query_document: &Query<&HydaDocument>) -> Option<Entity> {
+
+    let mut current = entity;
+
+    loop {
+        if query_document.contains(current) {
+            return Some(current);
+        }
+
+        match get_hierarchy_parent(current, query_parent, query_inline) {
+            Some(p) => current = p,
+            None => return None,
+        }
+    }
+}
+
+// The element with that id in the document of "root".
+pub(crate) fn find_element_by_id(root: Entity, id: &str, query_named: &Query<(Entity, &Name)>, query_parent: &Query<&Parent>, query_inline: &Query<&HydaInlineIn>, query_document: &Query<&HydaDocument>) -> Option<Entity> {
+
+    for (entity, name) in query_named {
+        if name.as_str() != id || get_document_root(entity, query_parent, query_inline, query_document) != Some(root) {
+            continue;
+        }
+
+        // Inline elements don't have a node to scroll to, the block they're in is used instead.
+        let mut target = entity;
+
+        while let Ok(i) = query_inline.get(target) {
+            target = i.0;
+        }
+
+        return Some(target);
+    }
+
+    return None;
+}
+
+pub(crate) fn follow_links(
+    mut click_events: EventReader<HydaClick>,
+    mut scroll_requests: EventWriter<HydaScrollRequest>,
+    mut navigation: ResMut<HydaNavigation>,
+    query_clickable: Query<&HydaClickable>,
+    query_document: Query<&HydaDocument>,
+    query_parent: Query<&Parent>,
+    query_inline: Query<&HydaInlineIn>,
+    query_named: Query<(Entity, &Name)>,
+) {
+    for click in click_events.read() {
+        let Ok(clickable) = query_clickable.get(click.entity) else { continue; };
+
+        if clickable.tag_name() != "a" {
+            continue;
+        }
+
+        let Some(href) = click.attributes.get("href") else { continue; };
+        let Some(root) = get_document_root(click.entity, &query_parent, &query_inline, &query_document) else { continue; };
+
+        let (path, fragment) = split_fragment(href);
+
+        // "#fragment" on its own scrolls inside of the current page.
+        if path.is_empty() {
+            if let Some(f) = fragment {
+                match find_element_by_id(root, f, &query_named, &query_parent, &query_inline, &query_document) {
+                    Some(element) => { scroll_requests.send(HydaScrollRequest::ToElement { element: element, smooth: None }); },
+                    None => warn!("bevy_hyda: there's no element with id \"{}\" to scroll to", f),
+                }
+            }
+
+            continue;
+        }
+
+        if !is_relative_html_link(path) {
+            continue;
+        }
+
+        let document = query_document.get(root).unwrap();
+        let final_path = resolve_relative_path(document.url(), path);
+
+        navigation.pending.push(HydaNavigationRequest::Go {
+            root: root,
+            url: final_path.to_string_lossy().to_string(),
+            fragment: fragment.map(|f| f.to_string()),
+        });
+    }
+}
+
+fn load_page(url: &str) -> Option<HydaAST> {
+
+    if !Path::new(url).exists() {
+        warn!("bevy_hyda: can't navigate to \"{}\", the file doesn't exist", url);
+        return None;
+    }
+
+    return Some(html_file(url.to_string()));
+}
+
+pub(crate) fn process_navigation(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut navigation: ResMut<HydaNavigation>,
+    query_root: Query<(Option<&Parent>, Option<&TargetCamera>)>,
+    query_document: Query<&HydaDocument>,
+) {
+    if navigation.pending.is_empty() {
+        return;
+    }
+
+    // The histories of the documents that were despawned.
+    navigation.histories.retain(|r, _| query_document.contains(*r));
+
+    let requests = std::mem::take(&mut navigation.pending);
+
+    for request in requests {
+        let (root, history, fragment, new_page) = match request {
+            HydaNavigationRequest::Go { root, url, fragment } => {
+                let Some(new_page) = load_page(&url) else { continue; };
+
+                let mut history = navigation.histories.remove(&root).unwrap_or_default();
+
+                // The page we come from is the first entry of the history, unless it isn't a file we can load
+                // again (like the ones from html_string()).
+                if history.entries.is_empty() {
+                    if let Ok(document) = query_document.get(root) {
+                        if !document.url().is_empty() && Path::new(document.url()).exists() {
+                            history.entries.push(document.url().to_string());
+                        }
+                    }
+                }
+                // Going somewhere new drops everything that was "forward".
+                else {
+                    history.entries.truncate(history.index + 1);
+                }
+
+                history.entries.push(url);
+                history.index = history.entries.len() - 1;
+
+                (root, history, fragment, new_page)
+            },
+            HydaNavigationRequest::Back { root } | HydaNavigationRequest::Forward { root } => {
+                let Some(history) = navigation.histories.get(&root) else { continue; };
+
+                let index = match request {
+                    HydaNavigationRequest::Back { .. } if history.can_go_back() => history.index - 1,
+                    HydaNavigationRequest::Forward { .. } if history.can_go_forward() => history.index + 1,
+                    _ => continue,
+                };
+
+                let Some(new_page) = load_page(&history.entries[index]) else { continue; };
+
+                let mut history = navigation.histories.remove(&root).unwrap();
+                history.index = index;
+
+                (root, history, None, new_page)
+            },
+        };
+
+        // The new page goes wherever the old one was (mounted in a node, or rendered by a camera).
+        let (parent, camera) = query_root.get(root).ok()
+            .map(|(p, c)| (p.map(|p| p.get()), c.map(|c| c.0)))
+            .unwrap_or((None, None));
+
+        if let Some(e) = commands.get_entity(root) {
+            e.despawn_recursive();
+        }
+
+        let new_root = match (parent, camera) {
+            (Some(p), _) => new_page.spawn_ui_into(&mut commands, &asset_server, p),
+            (None, Some(c)) => new_page.spawn_ui_with_camera(&mut commands, &asset_server, c),
+            (None, None) => new_page.spawn_ui(&mut commands, &asset_server),
+        };
+
+        // The history (and the back/forward buttons) follow the document to its new root.
+        navigation.histories.insert(new_root, history);
+        navigation.pending_fragments.remove(&root);
+
+        if let Some(f) = fragment {
+            navigation.pending_fragments.insert(new_root, f);
+        }
+    }
+}
+
+// The new page needs a frame to be laid out before we can scroll to its #fragment.
+pub(crate) fn scroll_to_pending_fragment(
+    mut scroll_requests: EventWriter<HydaScrollRequest>,
+    mut navigation: ResMut<HydaNavigation>,
+    query_named: Query<(Entity, &Name)>,
+    query_parent: Query<&Parent>,
+    query_inline: Query<&HydaInlineIn>,
+    query_document: Query<&HydaDocument>,
+    query_node: Query<&Node>,
+) {
+    if navigation.pending_fragments.is_empty() {
+        return;
+    }
+
+    let mut scrolled: Vec<Entity> = Vec::new();
+
+    for (root, fragment) in &navigation.pending_fragments {
+        let Some(element) = find_element_by_id(*root, fragment, &query_named, &query_parent, &query_inline, &query_document) else {
+            // Still not spawned.
+            continue;
+        };
+
+        let Ok(node) = query_node.get(element) else { continue; };
+
+        if node.size() == Vec2::ZERO {
+            continue;
+        }
+
+        scroll_requests.send(HydaScrollRequest::ToElement { element: element, smooth: None });
+        scrolled.push(*root);
+    }
+
+    for r in scrolled {
+        navigation.pending_fragments.remove(&r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_query_and_fragment() {
+        assert_eq!(split_fragment("page.html"), ("page.html", None));
+        assert_eq!(split_fragment("page.html?tab=2"), ("page.html", None));
+        assert_eq!(split_fragment("page.html?tab=2#top"), ("page.html", Some("top")));
+        assert_eq!(split_fragment("page.html#top?x"), ("page.html", Some("top?x")));
+        assert_eq!(split_fragment("#top"), ("", Some("top")));
+    }
+
+    #[test]
+    fn relative_html_links() {
+        for href in ["page.html?tab=2", "page.html#top?x", "menus/Options.HTM#audio"] {
+            assert!(is_relative_html_link(split_fragment(href).0), "\"{}\" should be a link to a document", href);
+        }
+
+        for href in ["https://example.com/page.html", "game://start", "image.png?v=2"] {
+            assert!(!is_relative_html_link(split_fragment(href).0), "\"{}\" shouldn't be a link to a document", href);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::{BevyHydaStyle, HydaCustomStyle, HydaFocusable, HydaInvalid, HydaScrolling, add_style, get_default_firasans};
+use crate::scroll::get_depth;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HydaPseudoClass {
+    Hover,
+    Active,
+    Focus,
+    FocusVisible,
+    Invalid,
+}
+
+impl HydaPseudoClass {
+    pub fn as_css(&self) -> &'static str {
+        match self {
+            HydaPseudoClass::Hover => ":hover",
+            HydaPseudoClass::Active => ":active",
+            HydaPseudoClass::Focus => ":focus",
+            HydaPseudoClass::FocusVisible => ":focus-visible",
+            HydaPseudoClass::Invalid => ":invalid",
+        }
+    }
+}
+
+// A rule that only applies while the element is in all of these states (like "button:hover").
+#[derive(Debug, Clone)]
+pub struct HydaDynamicStyle {
+    pub(crate) pseudo_classes: Vec<HydaPseudoClass>,
+    pub(crate) style: BevyHydaStyle,
+}
+
+// The element that currently has focus, "visible" is for :focus-visible (keyboard focus).
+#[derive(Component, Debug, Clone, Default)]
+pub struct HydaFocused {
+    pub visible: bool,
+}
+
+// Spawned on elements that have :hover, :active or :focus rules.
+#[derive(Component, Debug, Clone)]
+pub struct HydaStyleStates {
+    base: BevyHydaStyle,
+    current: BevyHydaStyle,
+    dynamic_styles: Vec<HydaDynamicStyle>,
+    active: Vec<HydaPseudoClass>,
+}
+
+impl HydaStyleStates {
+    pub fn new(base: BevyHydaStyle, dynamic_styles: Vec<HydaDynamicStyle>) -> Self {
+        Self {
+            base: base.clone(),
+            current: base,
+            dynamic_styles: dynamic_styles,
+            active: Vec::new(),
+        }
+    }
+
+    pub fn active_pseudo_classes(&self) -> &Vec<HydaPseudoClass> {
+        return &self.active;
+    }
+
+    pub fn current_style(&self) -> &BevyHydaStyle {
+        return &self.current;
+    }
+
+    // After a restyle: the new style (and rules) it's based on, keeping the states it's in.
+    pub(crate) fn set_base(&mut self, base: BevyHydaStyle, dynamic_styles: Vec<HydaDynamicStyle>) {
+        self.base = base;
+        self.dynamic_styles = dynamic_styles;
+        self.current = self.compose(&self.active);
+    }
+
+    fn compose(&self, active: &Vec<HydaPseudoClass>) -> BevyHydaStyle {
+
+        let mut get_style = self.base.clone();
+
+        for ds in &self.dynamic_styles {
+            if ds.pseudo_classes.iter().all(|pc| active.contains(pc)) {
+                add_style(&mut get_style, &ds.style);
+            }
+        }
+
+        return get_style;
+    }
+}
+
+fn get_active_pseudo_classes(interaction: &Interaction, focused: Option<&HydaFocused>, invalid: Option<&HydaInvalid>) -> Vec<HydaPseudoClass> {
+
+    let mut active: Vec<HydaPseudoClass> = Vec::new();
+
+    match interaction {
+        Interaction::Pressed => {
+            active.push(HydaPseudoClass::Hover);
+            active.push(HydaPseudoClass::Active);
+        },
+        Interaction::Hovered => active.push(HydaPseudoClass::Hover),
+        Interaction::None => {},
+    }
+
+    if let Some(f) = focused {
+        active.push(HydaPseudoClass::Focus);
+
+        if f.visible {
+            active.push(HydaPseudoClass::FocusVisible);
+        }
+    }
+
+    if invalid.is_some() {
+        active.push(HydaPseudoClass::Invalid);
+    }
+
+    return active;
+}
+
+// Text sections only keep the final color/size/font, so we change the ones that
+// came from the element itself (the ones still equal to its old style).
+pub(crate) fn restyle_text(text: &mut Text, old_style: &BevyHydaStyle, new_style: &BevyHydaStyle, asset_server: &AssetServer) {
+
+    let old_font: Option<Handle<Font>> = old_style.font_weight.map(|w| asset_server.load(get_default_firasans(w)));
+    let new_font: Option<Handle<Font>> = new_style.font_weight.map(|w| asset_server.load(get_default_firasans(w)));
+
+    for section in &mut text.sections {
+        if let (Some(old_color), Some(new_color)) = (old_style.color, new_style.color) {
+            if section.style.color == old_color {
+                section.style.color = new_color;
+            }
+        }
+
+        if let (Some(old_size), Some(new_size)) = (old_style.font_size, new_style.font_size) {
+            if section.style.font_size == old_size {
+                section.style.font_size = new_size;
+            }
+        }
+
+        if let (Some(old_f), Some(new_f)) = (&old_font, &new_font) {
+            if section.style.font == *old_f && old_f != new_f {
+                section.style.font = new_f.clone();
+            }
+        }
+    }
+}
+
+// The text entities that belong to an element, looking inside of its scroll content node if it has one.
+pub(crate) fn get_text_children(entity: Entity, query_children: &Query<&Children>, query_scrolling: &Query<(), With<HydaScrolling>>) -> Vec<Entity> {
+
+    let mut text_children: Vec<Entity> = Vec::new();
+
+    if let Ok(children) = query_children.get(entity) {
+        for c in children {
+            if query_scrolling.contains(*c) {
+                text_children.append(&mut get_text_children(*c, query_children, query_scrolling));
+            }
+            else {
+                text_children.push(*c);
+            }
+        }
+    }
+
+    return text_children;
+}
+
+pub(crate) fn apply_dynamic_styles(
+    asset_server: Res<AssetServer>,
+    mut query_states: Query<(Entity, &Interaction, Option<&HydaFocused>, Option<&HydaInvalid>, &mut HydaStyleStates, &mut Style, &mut BackgroundColor, Has<HydaCustomStyle>)>,
+    mut query_text: Query<&mut Text>,
+    query_children: Query<&Children>,
+    query_scrolling: Query<(), With<HydaScrolling>>,
+) {
+    for (entity, interaction, focused, invalid, mut states, mut style, mut background_color, has_custom_style) in &mut query_states {
+
+        let active = get_active_pseudo_classes(interaction, focused, invalid);
+
+        if active == states.active {
+            continue;
+        }
+
+        let new_style = states.compose(&active);
+
+        let new_bevy_style = new_style.to_bevy_style();
+
+        if *style != new_bevy_style && !has_custom_style {
+            *style = new_bevy_style;
+        }
+
+        if background_color.0 != new_style.background_color.unwrap() {
+            background_color.0 = new_style.background_color.unwrap();
+        }
+
+        for c in get_text_children(entity, &query_children, &query_scrolling) {
+            if let Ok(mut text) = query_text.get_mut(c) {
+                restyle_text(&mut text, &states.current, &new_style, &asset_server);
+            }
+        }
+
+        states.current = new_style;
+        states.active = active;
+    }
+}
+
+// Clicking a focusable element focuses it, clicking anywhere else takes the focus away.
+pub(crate) fn focus_on_click(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    query_pressed: Query<(Entity, &Interaction), (Changed<Interaction>, With<HydaFocusable>)>,
+    query_focused: Query<Entity, With<HydaFocused>>,
+    query_parent: Query<&Parent>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // Nested elements are all pressed at the same time, the innermost one gets the focus.
+    let mut target: Option<(Entity, usize)> = None;
+
+    for (entity, interaction) in &query_pressed {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let depth = get_depth(entity, &query_parent);
+
+        if target.is_none() || target.unwrap().1 < depth {
+            target = Some((entity, depth));
+        }
+    }
+
+    for e in &query_focused {
+        if Some(e) != target.map(|t| t.0) {
+            commands.entity(e).remove::<HydaFocused>();
+        }
+    }
+
+    if let Some((e, _)) = target {
+        commands.entity(e).insert(HydaFocused { visible: false });
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use ego_tree::NodeId;
+use scraper::{ElementRef, Selector};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{HydaDocument, HydaElement, HydaHtml};
+
+// Finds spawned elements with CSS selectors, like "document.querySelectorAll()".
+// The selectors run on the parsed HTML of each document (the same engine used for styling),
+// so anything that scraper supports works here too.
+#[derive(SystemParam)]
+pub struct HydaQuery<'w, 's> {
+    query_documents: Query<'w, 's, (Entity, &'static HydaDocument)>,
+    query_elements: Query<'w, 's, (Entity, &'static HydaElement)>,
+}
+
+impl<'w, 's> HydaQuery<'w, 's> {
+    // All the elements matching "selector" in every document, in document order.
+    pub fn select(&self, selector: &str) -> Vec<Entity> {
+        let Some(final_selector) = parse_selector(selector) else { return Vec::new(); };
+
+        let mut result: Vec<Entity> = Vec::new();
+
+        for (_, document) in &self.query_documents {
+            let html = document.html().lock().unwrap();
+            let node_ids: Vec<NodeId> = html.select(&final_selector).map(|e| e.id()).collect();
+
+            result.extend(self.get_entities(document.html(), &node_ids));
+        }
+
+        return result;
+    }
+
+    pub fn select_one(&self, selector: &str) -> Option<Entity> {
+        return self.select(selector).into_iter().next();
+    }
+
+    // Same as select(), but only looking inside of "scope" (which isn't included).
+    pub fn select_in(&self, scope: Entity, selector: &str) -> Vec<Entity> {
+        let Some(final_selector) = parse_selector(selector) else { return Vec::new(); };
+        let Some(source) = self.element(scope).and_then(|e| e.source()) else { return Vec::new(); };
+
+        let html = source.html().lock().unwrap();
+
+        let Some(scope_element) = html.tree.get(source.node_id()).and_then(ElementRef::wrap) else { return Vec::new(); };
+
+        let node_ids: Vec<NodeId> = scope_element.select(&final_selector).map(|e| e.id()).collect();
+
+        return self.get_entities(source.html(), &node_ids);
+    }
+
+    pub fn select_one_in(&self, scope: Entity, selector: &str) -> Option<Entity> {
+        return self.select_in(scope, selector).into_iter().next();
+    }
+
+    pub fn element(&self, entity: Entity) -> Option<&HydaElement> {
+        return self.query_elements.get(entity).ok().map(|(_, e)| e);
+    }
+
+    // Elements that aren't spawned (like the ones in <head>) are skipped.
+    fn get_entities(&self, html: &HydaHtml, node_ids: &Vec<NodeId>) -> Vec<Entity> {
+        let entities: HashMap<NodeId, Entity> = self.query_elements.iter()
+            .filter_map(|(entity, element)| element.source().map(|s| (entity, s)))
+            .filter(|(_, source)| Arc::ptr_eq(source.html(), html))
+            .map(|(entity, source)| (source.node_id(), entity))
+            .collect();
+
+        return node_ids.iter().filter_map(|n| entities.get(n).cloned()).collect();
+    }
+}
+
+fn parse_selector(selector: &str) -> Option<Selector> {
+    match Selector::parse(selector) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            warn!("bevy_hyda: \"{}\" isn't a valid selector: {:?}", selector, e);
+            None
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use crate::tests::{test_app, spawn_html};
+
+    fn ids(world: &World, entities: &Vec<Entity>) -> Vec<String> {
+        return entities.iter().filter_map(|e| world.get::<HydaElement>(*e).and_then(|h| h.id.clone())).collect();
+    }
+
+    #[test]
+    fn select_in_document_order() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<div id=\"menu\"><button id=\"play\" class=\"item\">Play</button><div><button id=\"options\" class=\"item\">Options</button></div><button id=\"quit\" class=\"item\" disabled>Quit</button></div><button id=\"back\" class=\"item\">Back</button>");
+
+        let world = app.world_mut();
+        let mut state: SystemState<HydaQuery> = SystemState::new(world);
+        let query = state.get(world);
+
+        assert_eq!(ids(world, &query.select(".item")), vec!["play", "options", "quit", "back"]);
+        assert_eq!(ids(world, &query.select("#menu > .item")), vec!["play", "quit"]);
+        assert_eq!(ids(world, &query.select(".item:not([disabled])")), vec!["play", "options", "back"]);
+        assert_eq!(query.select_one("#options").and_then(|e| query.element(e)).map(|e| e.tag.as_str()), Some("button"));
+        assert_eq!(query.select_one("#missing"), None);
+
+        // Not spawned, so there's no entity for them.
+        assert!(query.select("head, title").is_empty());
+    }
+
+    #[test]
+    fn select_inside_of_an_element() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<ul id=\"weapons\"><li id=\"sword\">Sword</li></ul><ul id=\"armor\"><li id=\"shield\">Shield</li></ul>");
+
+        let world = app.world_mut();
+        let mut state: SystemState<HydaQuery> = SystemState::new(world);
+        let query = state.get(world);
+
+        let armor = query.select_one("#armor").unwrap();
+
+        assert_eq!(ids(world, &query.select_in(armor, "li")), vec!["shield"]);
+        assert_eq!(ids(world, &query.select_one_in(armor, "li").into_iter().collect()), vec!["shield"]);
+        // The scope itself isn't included.
+        assert!(query.select_in(armor, "#armor").is_empty());
+    }
+
+    #[test]
+    fn invalid_selectors_match_nothing() {
+        let mut app = test_app();
+        spawn_html(&mut app, "<p id=\"text\">Hi</p>");
+
+        let world = app.world_mut();
+        let mut state: SystemState<HydaQuery> = SystemState::new(world);
+        let query = state.get(world);
+
+        assert!(query.select("p >").is_empty());
+        assert_eq!(query.select_one("[id="), None);
+    }
+}
@@ -0,0 +1,354 @@
+use bevy::prelude::*;
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node as HtmlNode};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{
+    BevyHydaStyle, HydaComputedStyle, HydaCustomStyle, HydaDocument, HydaElement, HydaHtml, HydaStyleSheet, HydaStyleStates, HydaText,
+    compose_document_style, compose_final_style, get_default_firasans, get_dynamic_styles, is_in_scope,
+};
+
+// Elements whose classes, attributes or state changed, so their style has to be computed again.
+// Only they (and the descendants that end up with a different style) get restyled, at the end of the frame.
+#[derive(Component, Debug, Clone, Default)]
+pub struct HydaRestyle;
+
+// Runs the selectors again on the elements that a change in "roots" can affect (for
+// descendant and sibling combinators, the whole subtree of each one of them).
+// Returns the elements that started or stopped matching any of the rules.
+pub(crate) fn refresh_selectors(html: &Html, styles: &mut Vec<HydaStyleSheet>, roots: &Vec<NodeId>) -> HashSet<NodeId> {
+
+    let mut scope: HashSet<NodeId> = HashSet::new();
+
+    for r in roots {
+        if let Some(n) = html.tree.get(*r) {
+            scope.extend(n.descendants().filter(|d| d.value().is_element()).map(|d| d.id()));
+        }
+    }
+
+    let mut changed: HashSet<NodeId> = HashSet::new();
+
+    for s in styles {
+        let Some(selector) = &s.parsed_selector else { continue; };
+
+        let before: HashSet<NodeId> = s.node_ids.iter().filter(|n| scope.contains(n)).copied().collect();
+
+        s.node_ids.retain(|n| !scope.contains(n));
+
+        for id in &scope {
+            let Some(element) = html.tree.get(*id).and_then(ElementRef::wrap) else { continue; };
+
+            let matches = selector.matches(&element) && is_in_scope(&element, &s.scope);
+
+            if matches {
+                s.node_ids.push(*id);
+            }
+
+            if matches != before.contains(id) {
+                changed.insert(*id);
+            }
+        }
+    }
+
+    return changed;
+}
+
+struct HydaRestyleContext<'a> {
+    styles: &'a Vec<HydaStyleSheet>,
+    entities: HashMap<NodeId, Entity>,
+    // Elements that need a new style, and the ones that have any of them inside.
+    changed: HashSet<NodeId>,
+    visit: HashSet<NodeId>,
+    // What the text sections of each restyled element should look like.
+    text_styles: HashMap<Entity, TextStyle>,
+}
+
+fn get_text_style(style: &BevyHydaStyle, asset_server: &AssetServer) -> TextStyle {
+    TextStyle {
+        font: asset_server.load(get_default_firasans(style.font_weight.unwrap())),
+        font_size: style.font_size.unwrap(),
+        color: style.color.unwrap(),
+    }
+}
+
+// Writes the new style of one element, only touching the components that actually change
+// (so the layout and the text don't get recomputed for nothing).
+fn apply_style(world: &mut World, context: &mut HydaRestyleContext, entity: Entity, node_id: NodeId, new_style: &BevyHydaStyle) {
+
+    let dynamic_styles = get_dynamic_styles(context.styles, node_id);
+
+    let mut final_style = new_style.clone();
+
+    if world.get::<Node>(entity).is_some() {
+        if dynamic_styles.is_empty() {
+            world.entity_mut(entity).remove::<HydaStyleStates>();
+        }
+        else if let Some(mut states) = world.get_mut::<HydaStyleStates>(entity) {
+            // The states it's in (hover, focus...) still apply on top of the new style.
+            states.set_base(new_style.clone(), dynamic_styles);
+            final_style = states.current_style().clone();
+        }
+        else {
+            if world.get::<Interaction>(entity).is_none() {
+                world.entity_mut(entity).insert(Interaction::default());
+            }
+
+            world.entity_mut(entity).insert(HydaStyleStates::new(new_style.clone(), dynamic_styles));
+        }
+
+        // Unless a custom element has a layout of its own.
+        let has_custom_style = world.get::<HydaCustomStyle>(entity).is_some();
+
+        if let (Some(mut style), false) = (world.get_mut::<Style>(entity), has_custom_style) {
+            style.set_if_neq(final_style.to_bevy_style());
+        }
+
+        if let Some(mut background_color) = world.get_mut::<BackgroundColor>(entity) {
+            background_color.set_if_neq(BackgroundColor(final_style.background_color.unwrap()));
+        }
+    }
+
+    let text_style = get_text_style(&final_style, world.resource::<AssetServer>());
+    context.text_styles.insert(entity, text_style);
+
+    if let Some(mut computed) = world.get_mut::<HydaComputedStyle>(entity) {
+        if computed.style() != new_style {
+            *computed = HydaComputedStyle::new(new_style.clone());
+        }
+    }
+}
+
+fn restyle_node(world: &mut World, context: &mut HydaRestyleContext, node: NodeRef<HtmlNode>, parent_style: &BevyHydaStyle, parent_changed: bool) {
+
+    // Text, or elements that weren't spawned (like <option> or the ones in <head>).
+    let Some(entity) = context.entities.get(&node.id()).copied() else { return; };
+
+    let Some(old_style) = world.get::<HydaComputedStyle>(entity).map(|c| c.style().clone()) else { return; };
+
+    let mut style = old_style.clone();
+
+    if parent_changed || context.changed.contains(&node.id()) {
+        style = match node.value().is_document() {
+            true => compose_document_style(context.styles, parent_style, node.id()),
+            false => compose_final_style(context.styles, parent_style, node.id()),
+        };
+
+        // The rules matching it might have changed while the result stays the same (dynamic ones included).
+        apply_style(world, context, entity, node.id(), &style);
+    }
+
+    // Inherited properties only reach the descendants if the style is different.
+    let is_changed = style != old_style;
+
+    for c in node.children() {
+        if is_changed || context.visit.contains(&c.id()) {
+            restyle_node(world, context, c, &style, is_changed);
+        }
+    }
+}
+
+fn update_text_sections(world: &mut World, text_styles: &HashMap<Entity, TextStyle>) {
+
+    if text_styles.is_empty() {
+        return;
+    }
+
+    let mut query_text = world.query::<(&HydaText, &mut Text)>();
+
+    for (hyda_text, mut text) in query_text.iter_mut(world) {
+        for (i, section_element) in hyda_text.section_elements.iter().enumerate() {
+            let Some(new_style) = text_styles.get(section_element) else { continue; };
+
+            let old_style = &text.sections[i].style;
+
+            if old_style.font != new_style.font || old_style.font_size != new_style.font_size || old_style.color != new_style.color {
+                text.sections[i].style = new_style.clone();
+            }
+        }
+    }
+}
+
+fn restyle_document(world: &mut World, document: Entity, html_handle: &HydaHtml, dirty: &Vec<NodeId>) {
+
+    let Some(mut styles) = world.get::<HydaDocument>(document).map(|d| d.styles.clone()) else { return; };
+
+    let html = html_handle.lock().unwrap();
+
+    // Siblings and descendants can match (or not) because of a change, so the parent is the scope.
+    let roots: Vec<NodeId> = dirty.iter()
+        .filter_map(|d| html.tree.get(*d))
+        .map(|n| n.parent().map(|p| p.id()).unwrap_or(n.id()))
+        .collect();
+
+    let mut changed = refresh_selectors(&html, &mut styles, &roots);
+    changed.extend(dirty.iter().copied());
+
+    let mut visit: HashSet<NodeId> = HashSet::new();
+
+    for c in &changed {
+        if let Some(n) = html.tree.get(*c) {
+            visit.extend(n.ancestors().map(|a| a.id()));
+            visit.insert(*c);
+        }
+    }
+
+    let mut query_elements = world.query::<(Entity, &HydaElement)>();
+
+    let entities: HashMap<NodeId, Entity> = query_elements.iter(world)
+        .filter_map(|(entity, element)| element.source().map(|s| (entity, s)))
+        .filter(|(_, source)| Arc::ptr_eq(source.html(), html_handle))
+        .map(|(entity, source)| (source.node_id(), entity))
+        .collect();
+
+    let mut context = HydaRestyleContext {
+        styles: &styles,
+        entities: entities,
+        changed: changed,
+        visit: visit,
+        text_styles: HashMap::new(),
+    };
+
+    // Same as when it was parsed, the document starts from the default style.
+    restyle_node(world, &mut context, html.tree.root(), &BevyHydaStyle::default(), false);
+
+    let text_styles = std::mem::take(&mut context.text_styles);
+
+    drop(context);
+
+    update_text_sections(world, &text_styles);
+
+    if let Some(mut hyda_document) = world.get_mut::<HydaDocument>(document) {
+        hyda_document.styles = styles;
+    }
+}
+
+pub(crate) fn restyle_elements(world: &mut World) {
+
+    let mut query_dirty = world.query_filtered::<(Entity, &HydaElement), With<HydaRestyle>>();
+
+    let dirty: Vec<(Entity, Option<(HydaHtml, NodeId)>)> = query_dirty.iter(world)
+        .map(|(e, element)| (e, element.source().map(|s| (s.html().clone(), s.node_id()))))
+        .collect();
+
+    if dirty.is_empty() {
+        return;
+    }
+
+    // Grouped by document, so the selectors and styles of each one are updated just once.
+    let mut query_documents = world.query::<(Entity, &HydaDocument)>();
+
+    let documents: Vec<(Entity, HydaHtml)> = query_documents.iter(world).map(|(e, d)| (e, d.html().clone())).collect();
+
+    for (document, html) in &documents {
+        let node_ids: Vec<NodeId> = dirty.iter()
+            .filter_map(|(_, s)| s.as_ref())
+            .filter(|(h, _)| Arc::ptr_eq(h, html))
+            .map(|(_, n)| *n)
+            .collect();
+
+        if !node_ids.is_empty() {
+            restyle_document(world, *document, html, &node_ids);
+        }
+    }
+
+    for (e, _) in dirty {
+        world.entity_mut(e).remove::<HydaRestyle>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::Selector;
+    use crate::{HydaDomCommandsExt, add_stylesheet, html_ast_impl};
+    use crate::dom::new_html_element;
+    use crate::tests::{test_app, spawn_ast};
+
+    fn parse(html: &str, css: &str) -> (Html, Vec<HydaStyleSheet>) {
+        let document = Html::parse_document(html);
+        let mut styles: Vec<HydaStyleSheet> = Vec::new();
+
+        add_stylesheet(css.to_string(), &mut styles, &document);
+
+        return (document, styles);
+    }
+
+    fn find(html: &Html, id: &str) -> NodeId {
+        return html.select(&Selector::parse(&format!("#{}", id)).unwrap()).next().unwrap().id();
+    }
+
+    // Like a class change does, returns the roots to refresh (the parent of the element).
+    fn set_class(html: &mut Html, id: &str, class: &str) -> Vec<NodeId> {
+        let node_id = find(html, id);
+        let mut node = html.tree.get_mut(node_id).unwrap();
+
+        let HtmlNode::Element(element) = node.value() else { panic!("#{} should be an element", id) };
+        let attributes = HashMap::from([("id".to_string(), id.to_string()), ("class".to_string(), class.to_string())]);
+        *element = new_html_element(element.name.clone(), &attributes);
+
+        return vec![html.tree.get(node_id).unwrap().parent().unwrap().id()];
+    }
+
+    #[test]
+    fn refresh_sibling_rules() {
+        let (mut html, mut styles) = parse("<ul><li id=\"a\">A</li><li id=\"b\">B</li><li id=\"c\">C</li></ul>", ".open + li { color: red; }");
+
+        let roots = set_class(&mut html, "a", "open");
+        let changed = refresh_selectors(&html, &mut styles, &roots);
+
+        assert_eq!(changed, HashSet::from([find(&html, "b")]));
+        assert_eq!(styles[0].node_ids, vec![find(&html, "b")]);
+
+        let roots = set_class(&mut html, "a", "");
+        let changed = refresh_selectors(&html, &mut styles, &roots);
+
+        assert_eq!(changed, HashSet::from([find(&html, "b")]));
+        assert!(styles[0].node_ids.is_empty());
+    }
+
+    #[test]
+    fn refresh_descendant_rules() {
+        let (mut html, mut styles) = parse("<div id=\"menu\"><p id=\"p\"><span id=\"s\">Hi</span></p></div><span id=\"other\">Bye</span>", ".open span { color: blue; } .open > p { color: red; }");
+
+        let roots = set_class(&mut html, "menu", "open");
+        let changed = refresh_selectors(&html, &mut styles, &roots);
+
+        assert_eq!(changed, HashSet::from([find(&html, "s"), find(&html, "p")]));
+        assert_eq!(styles[0].node_ids, vec![find(&html, "s")]);
+        assert_eq!(styles[1].node_ids, vec![find(&html, "p")]);
+
+        // Nothing changes if the class doesn't matter to any rule.
+        let roots = set_class(&mut html, "menu", "open wide");
+
+        assert!(refresh_selectors(&html, &mut styles, &roots).is_empty());
+    }
+
+    #[test]
+    fn restyle_after_adding_a_class() {
+        let dir = std::env::temp_dir().join("bevy_hyda_restyle_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("style.css"), ".open + li { color: rgb(255, 0, 0); }").unwrap();
+
+        let html = "<head><link rel=\"stylesheet\" href=\"style.css\"></head><ul><li id=\"a\">A</li><li id=\"b\">B</li></ul>";
+
+        let mut app = test_app();
+        app.add_systems(PostUpdate, restyle_elements);
+
+        spawn_ast(&mut app, &html_ast_impl(html.to_string(), dir.join("index.html").to_string_lossy().to_string()));
+
+        let world = app.world_mut();
+        let mut find = |id: &str| world.query::<(Entity, &HydaElement)>().iter(world).find(|(_, e)| e.id.as_deref() == Some(id)).unwrap().0;
+        let (a, b) = (find("a"), find("b"));
+
+        let red = Some(Color::srgb(1.0, 0.0, 0.0));
+
+        assert_ne!(app.world().get::<HydaComputedStyle>(b).unwrap().style().color, red);
+
+        app.world_mut().commands().add_class(a, "open");
+        app.update();
+
+        assert_eq!(app.world().get::<HydaComputedStyle>(b).unwrap().style().color, red);
+        assert_ne!(app.world().get::<HydaComputedStyle>(a).unwrap().style().color, red);
+    }
+}
@@ -0,0 +1,764 @@
+use bevy::prelude::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::input::touch::Touches;
+use bevy::input::gamepad::{Gamepads, GamepadAxis, GamepadAxisType};
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::PrimaryWindow;
+
+use crate::BevyHydaStyle;
+
+// Goes on the node that wraps the content of a scroll container (overflow: scroll/auto),
+// the container itself is always the parent of this node.
+#[derive(Component)]
+pub struct HydaScrolling {
+    position: Vec2,
+    target: Option<Vec2>,
+    smooth_target: bool,
+    scroll_x: bool,
+    scroll_y: bool,
+    smooth: bool,
+    velocity: Vec2,
+    touch_id: Option<u64>,
+    // How fast the momentum of a touch fling dies out, higher stops sooner.
+    pub friction: f32,
+    // Below this speed (in pixels per second) the momentum stops completely.
+    pub min_velocity: f32,
+    // Flings are capped to this speed (in pixels per second).
+    pub max_velocity: f32,
+}
+
+// How fast smooth scrolling catches up with its target, higher is faster.
+const SMOOTH_SCROLL_SPEED: f32 = 12.0;
+
+impl HydaScrolling {
+    pub fn new(scroll_x: bool, scroll_y: bool, smooth: bool) -> Self {
+        Self {
+            position: Vec2::ZERO,
+            target: None,
+            smooth_target: false,
+            scroll_x: scroll_x,
+            scroll_y: scroll_y,
+            smooth: smooth,
+            velocity: Vec2::ZERO,
+            touch_id: None,
+            friction: 4.0,
+            min_velocity: 10.0,
+            max_velocity: 5000.0,
+        }
+    }
+
+    // The current speed of a touch fling, in pixels per second.
+    pub fn velocity(&self) -> Vec2 {
+        return -self.velocity;
+    }
+
+    pub fn is_touch_dragging(&self) -> bool {
+        return self.touch_id.is_some();
+    }
+
+    // The current scroll offset, positive like "scrollLeft" and "scrollTop" in browsers.
+    pub fn offset(&self) -> Vec2 {
+        return -self.position;
+    }
+
+    // Where the container is scrolling to, if it's still moving.
+    pub fn target_offset(&self) -> Option<Vec2> {
+        return self.target.map(|t| -t);
+    }
+
+    // "smooth: None" follows the "scroll-behavior" of the container.
+    pub fn scroll_to(&mut self, offset: Vec2, smooth: Option<bool>) {
+        self.velocity = Vec2::ZERO;
+        self.target = Some(-offset);
+        self.smooth_target = smooth.unwrap_or(self.smooth);
+    }
+
+    pub fn scroll_by(&mut self, delta: Vec2, smooth: Option<bool>) {
+        let current = self.target_offset().unwrap_or(self.offset());
+        self.scroll_to(current + delta, smooth);
+    }
+
+    pub(crate) fn set_position(&mut self, position: Vec2, max_scroll: Vec2, style: &mut Style) {
+        if self.scroll_x {
+            self.position.x = position.x.clamp(-max_scroll.x, 0.);
+            style.left = Val::Px(self.position.x);
+        }
+
+        if self.scroll_y {
+            self.position.y = position.y.clamp(-max_scroll.y, 0.);
+            style.top = Val::Px(self.position.y);
+        }
+    }
+}
+
+impl Default for HydaScrolling {
+    fn default() -> Self {
+        Self::new(false, true, false)
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct HydaGamepadScrollSettings {
+    // The stick axes that scroll containers, "None" turns that direction off.
+    pub axis_x: Option<GamepadAxisType>,
+    pub axis_y: Option<GamepadAxisType>,
+    // Pixels per second with the stick fully tilted.
+    pub speed: f32,
+    pub dead_zone: f32,
+}
+
+impl Default for HydaGamepadScrollSettings {
+    fn default() -> Self {
+        Self {
+            axis_x: Some(GamepadAxisType::RightStickX),
+            axis_y: Some(GamepadAxisType::RightStickY),
+            speed: 800.0,
+            dead_zone: 0.15,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub enum HydaScrollRequest {
+    // Scroll "container" (the element with overflow: scroll/auto) to an absolute offset.
+    To { container: Entity, offset: Vec2, smooth: Option<bool> },
+    // Scroll "container" by a relative amount.
+    By { container: Entity, delta: Vec2, smooth: Option<bool> },
+    // Scroll every container holding "element" until it's fully visible.
+    IntoView { element: Entity, smooth: Option<bool> },
+    // Scroll every container holding "element" so it ends up at their top/left edge (like "#fragment" links).
+    ToElement { element: Entity, smooth: Option<bool> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HydaScrollAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl HydaScrollAxis {
+    fn get(&self, v: Vec2) -> f32 {
+        match self {
+            HydaScrollAxis::Horizontal => v.x,
+            HydaScrollAxis::Vertical => v.y,
+        }
+    }
+}
+
+// The track of a scrollbar, child of the scroll container.
+#[derive(Component)]
+pub struct HydaScrollbar {
+    content: Entity,
+    axis: HydaScrollAxis,
+}
+
+// The draggable part of the scrollbar, child of the track.
+#[derive(Component)]
+pub struct HydaScrollbarThumb {
+    content: Entity,
+    axis: HydaScrollAxis,
+    drag_start: Option<(f32, f32)>,
+}
+
+fn val_to_px(v: Val) -> f32 {
+    if let Val::Px(p) = v {
+        return p;
+    }
+
+    return 0.0;
+}
+
+// How far the content can be moved inside of its container, on each axis.
+pub(crate) fn get_max_scroll(content_node: &Node, container_node: &Node, container_style: &Style) -> Vec2 {
+
+    let padding = container_style.padding;
+    let border = container_style.border;
+
+    let inner_size = container_node.size() - Vec2::new(
+        val_to_px(padding.left) + val_to_px(padding.right) + val_to_px(border.left) + val_to_px(border.right),
+        val_to_px(padding.top) + val_to_px(padding.bottom) + val_to_px(border.top) + val_to_px(border.bottom),
+    );
+
+    return (content_node.size() - inner_size).max(Vec2::ZERO);
+}
+
+pub(crate) fn get_depth(entity: Entity, query_parent: &Query<&Parent>) -> usize {
+
+    let mut depth: usize = 0;
+    let mut current = entity;
+
+    while let Ok(p) = query_parent.get(current) {
+        current = p.get();
+        depth += 1;
+    }
+
+    return depth;
+}
+
+pub(crate) fn mouse_scroll(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut query_list: Query<(Entity, &mut HydaScrolling, &mut Style, &Parent, &Node)>,
+    query_container: Query<(&Node, &Style, &RelativeCursorPosition), Without<HydaScrolling>>,
+    query_parent: Query<&Parent>,
+) {
+    let shift_pressed = keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight);
+
+    for mouse_wheel_event in mouse_wheel_events.read() {
+
+        let mut delta = match mouse_wheel_event.unit {
+            MouseScrollUnit::Line => Vec2::new(mouse_wheel_event.x, mouse_wheel_event.y) * 20.,
+            MouseScrollUnit::Pixel => Vec2::new(mouse_wheel_event.x, mouse_wheel_event.y),
+        };
+
+        if shift_pressed {
+            delta = Vec2::new(delta.x + delta.y, 0.0);
+        }
+
+        // Only the innermost hovered container that can scroll in this direction gets the event.
+        let mut target: Option<(Entity, usize)> = None;
+
+        for (entity, scrolling_list, _, parent, _) in &query_list {
+            let Ok((_, _, cursor)) = query_container.get(parent.get()) else { continue; };
+
+            if !cursor.mouse_over() {
+                continue;
+            }
+
+            let can_scroll = (scrolling_list.scroll_y && delta.y != 0.0) || (scrolling_list.scroll_x && (delta.x != 0.0 || delta.y != 0.0));
+
+            if !can_scroll {
+                continue;
+            }
+
+            let depth = get_depth(entity, &query_parent);
+
+            if target.is_none() || target.unwrap().1 < depth {
+                target = Some((entity, depth));
+            }
+        }
+
+        let Some((target_entity, _)) = target else { continue; };
+        let Ok((_, mut scrolling_list, mut style, parent, list_node)) = query_list.get_mut(target_entity) else { continue; };
+        let Ok((container_node, container_style, _)) = query_container.get(parent.get()) else { continue; };
+
+        let max_scroll = get_max_scroll(list_node, container_node, container_style);
+
+        let mut final_delta = delta;
+
+        // A regular mouse wheel only scrolls vertically, so let it move horizontal-only containers too.
+        if !scrolling_list.scroll_y && final_delta.x == 0.0 {
+            final_delta.x = final_delta.y;
+        }
+
+        let new_position = scrolling_list.position + final_delta;
+        scrolling_list.target = None;
+        scrolling_list.velocity = Vec2::ZERO;
+        scrolling_list.set_position(new_position, max_scroll, &mut style);
+    }
+}
+
+pub(crate) fn spawn_scrollbars(commands: &mut Commands, container: Entity, content: Entity, style: &BevyHydaStyle) {
+
+    let width = style.scrollbar_width.unwrap();
+
+    // "scrollbar-width: none" still scrolls, it just doesn't show anything.
+    if width <= 0.0 {
+        return;
+    }
+
+    let thumb_color = style.scrollbar_thumb_color.unwrap();
+    let track_color = style.scrollbar_track_color.unwrap();
+
+    if style.scrolls_y() {
+        spawn_scrollbar(commands, container, content, HydaScrollAxis::Vertical, width, thumb_color, track_color);
+    }
+
+    if style.scrolls_x() {
+        spawn_scrollbar(commands, container, content, HydaScrollAxis::Horizontal, width, thumb_color, track_color);
+    }
+}
+
+fn spawn_scrollbar(commands: &mut Commands, container: Entity, content: Entity, axis: HydaScrollAxis, width: f32, thumb_color: Color, track_color: Color) {
+
+    // Both start hidden, update_scrollbars shows them once we know the content overflows.
+    let (track_style, thumb_style) = match axis {
+        HydaScrollAxis::Vertical => (
+            Style {
+                display: bevy::ui::Display::None,
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.0),
+                top: Val::Px(0.0),
+                width: Val::Px(width),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.0),
+                top: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Px(0.0),
+                ..default()
+            },
+        ),
+        HydaScrollAxis::Horizontal => (
+            Style {
+                display: bevy::ui::Display::None,
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.0),
+                bottom: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Px(width),
+                ..default()
+            },
+            Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.0),
+                top: Val::Px(0.0),
+                width: Val::Px(0.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+        ),
+    };
+
+    let track = commands.spawn((
+        NodeBundle {
+            style: track_style,
+            background_color: BackgroundColor(track_color),
+            z_index: ZIndex::Local(1),
+            ..default()
+        },
+        HydaScrollbar { content: content, axis: axis },
+    )).id();
+
+    let thumb = commands.spawn((
+        NodeBundle {
+            style: thumb_style,
+            background_color: BackgroundColor(thumb_color),
+            ..default()
+        },
+        Interaction::default(),
+        HydaScrollbarThumb { content: content, axis: axis, drag_start: None },
+    )).id();
+
+    commands.entity(track).add_child(thumb);
+    commands.entity(container).add_child(track);
+}
+
+pub(crate) fn update_scrollbars(
+    mut query_track: Query<(&HydaScrollbar, &mut Style, &Node, &Children), Without<HydaScrollbarThumb>>,
+    mut query_thumb: Query<&mut Style, (With<HydaScrollbarThumb>, Without<HydaScrollbar>)>,
+    query_content: Query<(&HydaScrolling, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style), (Without<HydaScrolling>, Without<HydaScrollbar>, Without<HydaScrollbarThumb>)>,
+) {
+    for (scrollbar, mut track_style, track_node, children) in &mut query_track {
+        let Ok((scrolling_list, content_node, parent)) = query_content.get(scrollbar.content) else { continue; };
+        let Ok((container_node, container_style)) = query_container.get(parent.get()) else { continue; };
+
+        let max_scroll = scrollbar.axis.get(get_max_scroll(content_node, container_node, container_style));
+
+        // Hide the scrollbar when everything fits.
+        let final_display = if max_scroll > 0.5 { bevy::ui::Display::Flex } else { bevy::ui::Display::None };
+
+        if track_style.display != final_display {
+            track_style.display = final_display;
+        }
+
+        if final_display == bevy::ui::Display::None {
+            continue;
+        }
+
+        let content_len = scrollbar.axis.get(content_node.size());
+        let track_len = scrollbar.axis.get(track_node.size());
+
+        if content_len <= 0.0 || track_len <= 0.0 {
+            continue;
+        }
+
+        let visible_len = content_len - max_scroll;
+        let thumb_len = (track_len * visible_len / content_len).max(20.0).min(track_len);
+        let thumb_offset = (-scrollbar.axis.get(scrolling_list.position) / max_scroll) * (track_len - thumb_len);
+
+        for c in children {
+            let Ok(mut thumb_style) = query_thumb.get_mut(*c) else { continue; };
+
+            match scrollbar.axis {
+                HydaScrollAxis::Vertical => {
+                    if thumb_style.height != Val::Px(thumb_len) || thumb_style.top != Val::Px(thumb_offset) {
+                        thumb_style.height = Val::Px(thumb_len);
+                        thumb_style.top = Val::Px(thumb_offset);
+                    }
+                },
+                HydaScrollAxis::Horizontal => {
+                    if thumb_style.width != Val::Px(thumb_len) || thumb_style.left != Val::Px(thumb_offset) {
+                        thumb_style.width = Val::Px(thumb_len);
+                        thumb_style.left = Val::Px(thumb_offset);
+                    }
+                },
+            }
+        }
+    }
+}
+
+pub(crate) fn drag_scrollbar_thumb(
+    mouse: Res<ButtonInput<MouseButton>>,
+    query_window: Query<&Window, With<PrimaryWindow>>,
+    mut query_thumb: Query<(&Interaction, &mut HydaScrollbarThumb, &Node, &Parent)>,
+    query_track: Query<&Node, (With<HydaScrollbar>, Without<HydaScrollbarThumb>)>,
+    mut query_content: Query<(&mut HydaScrolling, &mut Style, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style), Without<HydaScrolling>>,
+) {
+    let Ok(window) = query_window.get_single() else { return; };
+    let Some(cursor) = window.cursor_position() else { return; };
+
+    for (interaction, mut thumb, thumb_node, parent) in &mut query_thumb {
+
+        if !mouse.pressed(MouseButton::Left) {
+            thumb.drag_start = None;
+            continue;
+        }
+
+        let Ok((mut scrolling_list, mut style, content_node, content_parent)) = query_content.get_mut(thumb.content) else { continue; };
+
+        if *interaction == Interaction::Pressed && mouse.just_pressed(MouseButton::Left) {
+            thumb.drag_start = Some((thumb.axis.get(cursor), thumb.axis.get(scrolling_list.position)));
+        }
+
+        let Some((start_cursor, start_position)) = thumb.drag_start else { continue; };
+        let Ok(track_node) = query_track.get(parent.get()) else { continue; };
+        let Ok((container_node, container_style)) = query_container.get(content_parent.get()) else { continue; };
+
+        let max_scroll = get_max_scroll(content_node, container_node, container_style);
+        let free_len = thumb.axis.get(track_node.size()) - thumb.axis.get(thumb_node.size());
+
+        if free_len <= 0.0 {
+            continue;
+        }
+
+        // Moving the thumb down/right moves the content up/left.
+        let new_axis_position = start_position - (thumb.axis.get(cursor) - start_cursor) * (thumb.axis.get(max_scroll) / free_len);
+
+        let new_position = match thumb.axis {
+            HydaScrollAxis::Horizontal => Vec2::new(new_axis_position, scrolling_list.position.y),
+            HydaScrollAxis::Vertical => Vec2::new(scrolling_list.position.x, new_axis_position),
+        };
+
+        scrolling_list.target = None;
+        scrolling_list.velocity = Vec2::ZERO;
+        scrolling_list.set_position(new_position, max_scroll, &mut style);
+    }
+}
+
+// The scrolled content node of a container, "entity" can either be the element with
+// the overflow or the content node itself.
+fn get_scroll_content(entity: Entity, query_scrolling: &Query<(&mut HydaScrolling, &Node, &Parent, &GlobalTransform)>, query_children: &Query<&Children>) -> Option<Entity> {
+
+    if query_scrolling.contains(entity) {
+        return Some(entity);
+    }
+
+    if let Ok(children) = query_children.get(entity) {
+        for c in children {
+            if query_scrolling.contains(*c) {
+                return Some(*c);
+            }
+        }
+    }
+
+    return None;
+}
+
+pub(crate) fn handle_scroll_requests(
+    mut scroll_requests: EventReader<HydaScrollRequest>,
+    mut query_scrolling: Query<(&mut HydaScrolling, &Node, &Parent, &GlobalTransform)>,
+    query_element: Query<(&Node, &GlobalTransform), Without<HydaScrolling>>,
+    query_container: Query<(&Node, &Style), Without<HydaScrolling>>,
+    query_children: Query<&Children>,
+    query_parent: Query<&Parent>,
+) {
+    for request in scroll_requests.read() {
+        match request {
+            HydaScrollRequest::To { container, offset, smooth } => {
+                let Some(content) = get_scroll_content(*container, &query_scrolling, &query_children) else { continue; };
+                let Ok((mut scrolling_list, _, _, _)) = query_scrolling.get_mut(content) else { continue; };
+
+                scrolling_list.scroll_to(*offset, *smooth);
+            },
+            HydaScrollRequest::By { container, delta, smooth } => {
+                let Some(content) = get_scroll_content(*container, &query_scrolling, &query_children) else { continue; };
+                let Ok((mut scrolling_list, _, _, _)) = query_scrolling.get_mut(content) else { continue; };
+
+                scrolling_list.scroll_by(*delta, *smooth);
+            },
+            HydaScrollRequest::IntoView { element, smooth } => {
+                scroll_element_into_view(*element, *smooth, false, &mut query_scrolling, &query_element, &query_container, &query_parent);
+            },
+            HydaScrollRequest::ToElement { element, smooth } => {
+                scroll_element_into_view(*element, *smooth, true, &mut query_scrolling, &query_element, &query_container, &query_parent);
+            },
+        }
+    }
+}
+
+fn scroll_element_into_view(
+    element: Entity,
+    smooth: Option<bool>,
+    align_start: bool,
+    query_scrolling: &mut Query<(&mut HydaScrolling, &Node, &Parent, &GlobalTransform)>,
+    query_element: &Query<(&Node, &GlobalTransform), Without<HydaScrolling>>,
+    query_container: &Query<(&Node, &Style), Without<HydaScrolling>>,
+    query_parent: &Query<&Parent>,
+) {
+    let Ok((element_node, element_transform)) = query_element.get(element) else { return; };
+
+    let element_min = element_transform.translation().truncate() - element_node.size() / 2.0;
+    let element_max = element_min + element_node.size();
+
+    let mut current = element;
+
+    while let Ok(p) = query_parent.get(current) {
+        current = p.get();
+
+        let Ok((mut scrolling_list, content_node, content_parent, content_transform)) = query_scrolling.get_mut(current) else { continue; };
+        let Ok((container_node, container_style)) = query_container.get(content_parent.get()) else { continue; };
+
+        let max_scroll = get_max_scroll(content_node, container_node, container_style);
+        let visible_size = content_node.size() - max_scroll;
+
+        // Where the element is inside of the content, in the same units as the offset.
+        let content_min = content_transform.translation().truncate() - content_node.size() / 2.0;
+        let relative_min = element_min - content_min;
+        let relative_max = element_max - content_min;
+
+        let current_offset = scrolling_list.target_offset().unwrap_or(scrolling_list.offset());
+        let mut new_offset = current_offset;
+
+        for axis in 0..2 {
+            if align_start || relative_min[axis] < current_offset[axis] {
+                new_offset[axis] = relative_min[axis];
+            }
+            else if relative_max[axis] > current_offset[axis] + visible_size[axis] {
+                new_offset[axis] = (relative_max[axis] - visible_size[axis]).min(relative_min[axis]);
+            }
+        }
+
+        if new_offset != current_offset {
+            scrolling_list.scroll_to(new_offset, smooth);
+        }
+    }
+}
+
+pub(crate) fn animate_scroll(
+    time: Res<Time>,
+    mut query_list: Query<(&mut HydaScrolling, &mut Style, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style), Without<HydaScrolling>>,
+) {
+    for (mut scrolling_list, mut style, list_node, parent) in &mut query_list {
+        let Some(target) = scrolling_list.target else { continue; };
+        let Ok((container_node, container_style)) = query_container.get(parent.get()) else { continue; };
+
+        let max_scroll = get_max_scroll(list_node, container_node, container_style);
+        let mut target = target.clamp(-max_scroll, Vec2::ZERO);
+
+        // set_position() leaves the axes that don't scroll alone, so the target can't move on them either
+        // (or the position would never get there).
+        if !scrolling_list.scroll_x {
+            target.x = scrolling_list.position.x;
+        }
+
+        if !scrolling_list.scroll_y {
+            target.y = scrolling_list.position.y;
+        }
+
+        let mut new_position = target;
+
+        if scrolling_list.smooth_target {
+            let t = 1.0 - (-SMOOTH_SCROLL_SPEED * time.delta_seconds()).exp();
+            new_position = scrolling_list.position + (target - scrolling_list.position) * t;
+
+            if new_position.distance(target) < 0.5 {
+                new_position = target;
+            }
+        }
+
+        scrolling_list.set_position(new_position, max_scroll, &mut style);
+
+        if new_position == target {
+            scrolling_list.target = None;
+        }
+    }
+}
+
+fn node_contains_point(node: &Node, transform: &GlobalTransform, point: Vec2) -> bool {
+    return node.logical_rect(transform).contains(point);
+}
+
+pub(crate) fn touch_scroll(
+    time: Res<Time>,
+    touches: Res<Touches>,
+    mut query_list: Query<(Entity, &mut HydaScrolling, &mut Style, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style, &GlobalTransform), Without<HydaScrolling>>,
+    query_parent: Query<&Parent>,
+) {
+    // A new finger on the screen grabs the innermost container under it.
+    for touch in touches.iter_just_pressed() {
+        let mut target: Option<(Entity, usize)> = None;
+
+        for (entity, scrolling_list, _, _, parent) in &query_list {
+            if scrolling_list.touch_id.is_some() {
+                continue;
+            }
+
+            let Ok((container_node, _, container_transform)) = query_container.get(parent.get()) else { continue; };
+
+            if !node_contains_point(container_node, container_transform, touch.position()) {
+                continue;
+            }
+
+            let depth = get_depth(entity, &query_parent);
+
+            if target.is_none() || target.unwrap().1 < depth {
+                target = Some((entity, depth));
+            }
+        }
+
+        if let Some((target_entity, _)) = target {
+            if let Ok((_, mut scrolling_list, _, _, _)) = query_list.get_mut(target_entity) {
+                scrolling_list.touch_id = Some(touch.id());
+                scrolling_list.target = None;
+                scrolling_list.velocity = Vec2::ZERO;
+            }
+        }
+    }
+
+    let delta_seconds = time.delta_seconds();
+
+    for (_, mut scrolling_list, mut style, list_node, parent) in &mut query_list {
+        let Ok((container_node, container_style, _)) = query_container.get(parent.get()) else { continue; };
+        let max_scroll = get_max_scroll(list_node, container_node, container_style);
+
+        if let Some(id) = scrolling_list.touch_id {
+
+            // Once the finger is lifted, the momentum below takes over.
+            if touches.just_released(id) || touches.just_canceled(id) || touches.get_pressed(id).is_none() {
+                scrolling_list.touch_id = None;
+                continue;
+            }
+
+            let delta = touches.get_pressed(id).unwrap().delta();
+
+            if delta_seconds > 0.0 {
+                let max_velocity = scrolling_list.max_velocity;
+                let new_velocity = (delta / delta_seconds).clamp_length_max(max_velocity);
+                scrolling_list.velocity = scrolling_list.velocity * 0.2 + new_velocity * 0.8;
+            }
+
+            let new_position = scrolling_list.position + delta;
+            scrolling_list.set_position(new_position, max_scroll, &mut style);
+        }
+    }
+}
+
+pub(crate) fn apply_scroll_inertia(
+    time: Res<Time>,
+    mut query_list: Query<(&mut HydaScrolling, &mut Style, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style), Without<HydaScrolling>>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (mut scrolling_list, mut style, list_node, parent) in &mut query_list {
+        if scrolling_list.touch_id.is_some() || scrolling_list.velocity == Vec2::ZERO {
+            continue;
+        }
+
+        if scrolling_list.velocity.length() < scrolling_list.min_velocity {
+            scrolling_list.velocity = Vec2::ZERO;
+            continue;
+        }
+
+        let Ok((container_node, container_style)) = query_container.get(parent.get()) else { continue; };
+        let max_scroll = get_max_scroll(list_node, container_node, container_style);
+
+        let new_position = scrolling_list.position + scrolling_list.velocity * delta_seconds;
+        scrolling_list.set_position(new_position, max_scroll, &mut style);
+
+        // Hitting the edge stops the fling on that axis.
+        if scrolling_list.position.x != new_position.x {
+            scrolling_list.velocity.x = 0.0;
+        }
+
+        if scrolling_list.position.y != new_position.y {
+            scrolling_list.velocity.y = 0.0;
+        }
+
+        let friction = scrolling_list.friction;
+        scrolling_list.velocity *= (-friction * delta_seconds).exp();
+    }
+}
+
+pub(crate) fn gamepad_scroll(
+    time: Res<Time>,
+    settings: Res<HydaGamepadScrollSettings>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut query_list: Query<(Entity, &mut HydaScrolling, &mut Style, &Node, &Parent)>,
+    query_container: Query<(&Node, &Style, &RelativeCursorPosition), Without<HydaScrolling>>,
+    query_parent: Query<&Parent>,
+) {
+    let mut stick = Vec2::ZERO;
+
+    for gamepad in gamepads.iter() {
+        if let Some(axis_type) = settings.axis_x {
+            stick.x += axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+        }
+
+        if let Some(axis_type) = settings.axis_y {
+            stick.y += axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+        }
+    }
+
+    if stick.x.abs() < settings.dead_zone { stick.x = 0.0; }
+    if stick.y.abs() < settings.dead_zone { stick.y = 0.0; }
+
+    if stick == Vec2::ZERO {
+        return;
+    }
+
+    // The innermost hovered container gets the stick, if there's none we scroll the outermost one (the page).
+    let mut hovered_target: Option<(Entity, usize)> = None;
+    let mut page_target: Option<(Entity, usize)> = None;
+
+    for (entity, scrolling_list, _, _, parent) in &query_list {
+        let Ok((_, _, cursor)) = query_container.get(parent.get()) else { continue; };
+
+        if !scrolling_list.scroll_x && !scrolling_list.scroll_y {
+            continue;
+        }
+
+        let depth = get_depth(entity, &query_parent);
+
+        if cursor.mouse_over() && (hovered_target.is_none() || hovered_target.unwrap().1 < depth) {
+            hovered_target = Some((entity, depth));
+        }
+
+        if page_target.is_none() || page_target.unwrap().1 > depth {
+            page_target = Some((entity, depth));
+        }
+    }
+
+    let Some((target_entity, _)) = hovered_target.or(page_target) else { return; };
+    let Ok((_, mut scrolling_list, mut style, list_node, parent)) = query_list.get_mut(target_entity) else { return; };
+    let Ok((container_node, container_style, _)) = query_container.get(parent.get()) else { return; };
+
+    let max_scroll = get_max_scroll(list_node, container_node, container_style);
+
+    // Pushing the stick up/right moves the view up/right, so the content goes the other way on x.
+    let delta = Vec2::new(-stick.x, stick.y) * settings.speed * time.delta_seconds();
+
+    let new_position = scrolling_list.position + delta;
+    scrolling_list.target = None;
+    scrolling_list.velocity = Vec2::ZERO;
+    scrolling_list.set_position(new_position, max_scroll, &mut style);
+}
@@ -0,0 +1,488 @@
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::gamepad::{Gamepads, GamepadButton, GamepadButtonType};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::ui::FocusPolicy;
+use std::collections::HashMap;
+
+use crate::{HydaAST, HydaFocused, get_text_content};
+use crate::focus::gamepad_just_pressed;
+
+const SELECTED_ROW_COLOR: Color = Color::srgb(0.80, 0.87, 1.0);
+const HIGHLIGHTED_ROW_COLOR: Color = Color::srgb(0.91, 0.91, 0.93);
+const POPUP_COLOR: Color = Color::WHITE;
+
+// Sent when the user picks an option. "value" is the first selected one, "values" all of them (for "multiple").
+#[derive(Event, Debug, Clone)]
+pub struct HydaSelectChanged {
+    pub entity: Entity,
+    pub element_id: Option<String>,
+    pub name: Option<String>,
+    pub value: Option<String>,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HydaSelectOption {
+    pub value: String,
+    pub label: String,
+    pub disabled: bool,
+    // The label of the <optgroup> the option is in.
+    pub group: Option<String>,
+}
+
+// <select>, shown as a box with the selected option that opens a popup list,
+// or as a list that's always open with "multiple".
+#[derive(Component, Debug, Clone)]
+pub struct HydaSelect {
+    attributes: HashMap<String, String>,
+    options: Vec<HydaSelectOption>,
+    selected: Vec<usize>,
+    highlighted: Option<usize>,
+    open: bool,
+    label: Entity,
+    popup: Option<Entity>,
+    rows: Vec<(usize, Entity)>,
+    text_style: TextStyle,
+    group_text_style: TextStyle,
+}
+
+// One of the options in the list, "index" is the one in HydaSelect::options().
+#[derive(Component, Debug, Clone)]
+pub struct HydaSelectRow {
+    select: Entity,
+    index: usize,
+}
+
+impl HydaSelect {
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    pub fn options(&self) -> &Vec<HydaSelectOption> {
+        return &self.options;
+    }
+
+    pub fn is_multiple(&self) -> bool {
+        return self.attributes.contains_key("multiple");
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        return self.attributes.contains_key("disabled");
+    }
+
+    pub fn is_open(&self) -> bool {
+        return self.open;
+    }
+
+    pub fn selected_indices(&self) -> &Vec<usize> {
+        return &self.selected;
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        return self.selected.first().map(|i| self.options[*i].value.as_str());
+    }
+
+    pub fn values(&self) -> Vec<String> {
+        return self.selected.iter().map(|i| self.options[*i].value.clone()).collect();
+    }
+
+    // Selects the option at "index" (toggles it with "multiple"), returns false if it can't.
+    pub fn select(&mut self, index: usize) -> bool {
+        let Some(option) = self.options.get(index) else { return false; };
+
+        if option.disabled {
+            return false;
+        }
+
+        if self.is_multiple() {
+            match self.selected.iter().position(|i| *i == index) {
+                Some(p) => { self.selected.remove(p); },
+                None => { self.selected.push(index); self.selected.sort(); },
+            }
+        }
+        else {
+            if self.selected == vec![index] {
+                return false;
+            }
+
+            self.selected = vec![index];
+        }
+
+        return true;
+    }
+
+    fn selected_label(&self) -> String {
+        return self.selected.first().map(|i| self.options[*i].label.clone()).unwrap_or_default();
+    }
+
+    // Next option that isn't disabled, going up (-1) or down (1) from the highlighted one.
+    fn next_enabled(&self, direction: i32) -> Option<usize> {
+        let start = self.highlighted.or(self.selected.first().cloned());
+
+        let mut index = match start {
+            Some(s) => s as i32 + direction,
+            None => if direction > 0 { 0 } else { self.options.len() as i32 - 1 },
+        };
+
+        while index >= 0 && (index as usize) < self.options.len() {
+            if !self.options[index as usize].disabled {
+                return Some(index as usize);
+            }
+
+            index += direction;
+        }
+
+        return None;
+    }
+}
+
+pub(crate) fn get_select_options(content: &Vec<HydaAST>, group: Option<String>, options: &mut Vec<HydaSelectOption>, selected: &mut Vec<usize>) {
+    for c in content {
+        let HydaAST::HElement { tag_name, attributes, content: element_content, .. } = c else { continue; };
+
+        match tag_name.as_str() {
+            "option" => {
+                let text = get_text_content(element_content).split_whitespace().collect::<Vec<&str>>().join(" ");
+
+                if attributes.contains_key("selected") {
+                    selected.push(options.len());
+                }
+
+                options.push(HydaSelectOption {
+                    value: attributes.get("value").cloned().unwrap_or(text.clone()),
+                    label: attributes.get("label").cloned().unwrap_or(text),
+                    disabled: attributes.contains_key("disabled"),
+                    group: group.clone(),
+                });
+            },
+            "optgroup" => {
+                let first = options.len();
+
+                get_select_options(element_content, attributes.get("label").cloned().or(Some(String::new())), options, selected);
+
+                // Options inside of a disabled group are disabled too.
+                if attributes.contains_key("disabled") {
+                    for o in &mut options[first..] {
+                        o.disabled = true;
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+pub(crate) fn spawn_select(commands: &mut Commands, entity: Entity, attributes: &HashMap<String, String>, content: &Vec<HydaAST>, text_style: TextStyle, group_text_style: TextStyle) {
+
+    let mut options: Vec<HydaSelectOption> = Vec::new();
+    let mut selected: Vec<usize> = Vec::new();
+
+    get_select_options(content, None, &mut options, &mut selected);
+
+    let mut select = HydaSelect {
+        attributes: attributes.clone(),
+        options: options,
+        selected: selected,
+        highlighted: None,
+        open: false,
+        label: Entity::PLACEHOLDER,
+        popup: None,
+        rows: Vec::new(),
+        text_style: text_style,
+        group_text_style: group_text_style,
+    };
+
+    // With "multiple" the list is always there, instead of the label.
+    if select.is_multiple() {
+        select.rows = spawn_rows(commands, entity, entity, &select);
+    }
+    else {
+        select.selected.truncate(1);
+
+        // Without "selected", browsers show the first option that can be picked.
+        if select.selected.is_empty() {
+            if let Some(first) = select.options.iter().position(|o| !o.disabled) {
+                select.selected.push(first);
+            }
+        }
+
+        select.label = commands.spawn(TextBundle::from_section(select.selected_label(), select.text_style.clone())).id();
+        commands.entity(entity).add_child(select.label);
+    }
+
+    commands.entity(entity).insert((select, Interaction::default()));
+}
+
+fn spawn_rows(commands: &mut Commands, parent: Entity, select_entity: Entity, select: &HydaSelect) -> Vec<(usize, Entity)> {
+
+    let mut rows: Vec<(usize, Entity)> = Vec::new();
+    let mut current_group: Option<String> = None;
+
+    for (i, option) in select.options.iter().enumerate() {
+        if option.group != current_group {
+            current_group = option.group.clone();
+
+            if let Some(g) = &current_group {
+                let header = commands.spawn(TextBundle {
+                    text: Text::from_section(g.clone(), select.group_text_style.clone()),
+                    style: Style { padding: UiRect::new(Val::Px(4.0), Val::Px(4.0), Val::Px(1.0), Val::Px(1.0)), ..default() },
+                    ..default()
+                }).id();
+
+                commands.entity(parent).add_child(header);
+            }
+        }
+
+        let text_style = match option.disabled {
+            true => TextStyle { color: select.text_style.color.with_alpha(select.text_style.color.alpha() * 0.5), ..select.text_style.clone() },
+            false => select.text_style.clone(),
+        };
+
+        let text = commands.spawn(TextBundle::from_section(option.label.clone(), text_style)).id();
+
+        let row = commands.spawn((
+            NodeBundle {
+                style: Style {
+                    padding: UiRect::new(Val::Px(if option.group.is_some() { 16.0 } else { 4.0 }), Val::Px(4.0), Val::Px(1.0), Val::Px(1.0)),
+                    ..default()
+                },
+                focus_policy: FocusPolicy::Block,
+                ..default()
+            },
+            Interaction::default(),
+            HydaSelectRow { select: select_entity, index: i },
+        )).id();
+
+        commands.entity(row).add_child(text);
+        commands.entity(parent).add_child(row);
+
+        rows.push((i, row));
+    }
+
+    return rows;
+}
+
+fn open_popup(commands: &mut Commands, entity: Entity, select: &mut HydaSelect) {
+    if select.open || select.is_multiple() || select.is_disabled() {
+        return;
+    }
+
+    let popup = commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(100.0),
+                left: Val::Px(0.0),
+                min_width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::vertical(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(POPUP_COLOR),
+            focus_policy: FocusPolicy::Block,
+            // On top of everything, not only of its siblings.
+            z_index: ZIndex::Global(1000),
+            ..default()
+        },
+        Interaction::default(),
+    )).id();
+
+    select.rows = spawn_rows(commands, popup, entity, select);
+    select.popup = Some(popup);
+    select.highlighted = select.selected.first().cloned();
+    select.open = true;
+
+    commands.entity(entity).add_child(popup);
+}
+
+fn close_popup(commands: &mut Commands, select: &mut HydaSelect) {
+    if let Some(p) = select.popup.take() {
+        commands.entity(p).despawn_recursive();
+    }
+
+    select.rows.clear();
+    select.highlighted = None;
+    select.open = false;
+}
+
+fn send_select_changed(entity: Entity, select: &HydaSelect, changed_events: &mut EventWriter<HydaSelectChanged>) {
+    changed_events.send(HydaSelectChanged {
+        entity: entity,
+        element_id: select.attributes.get("id").cloned(),
+        name: select.attributes.get("name").cloned(),
+        value: select.value().map(|v| v.to_string()),
+        values: select.values(),
+    });
+}
+
+// Opens and closes the popup, and picks the option that gets clicked.
+pub(crate) fn select_mouse_input(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut changed_events: EventWriter<HydaSelectChanged>,
+    mut query_select: Query<(Entity, &Interaction, &mut HydaSelect)>,
+    query_rows: Query<(&Interaction, &HydaSelectRow), Changed<Interaction>>,
+    query_popup: Query<&Interaction, Without<HydaSelect>>,
+) {
+    for (interaction, row) in &query_rows {
+        let Ok((entity, _, mut select)) = query_select.get_mut(row.select) else { continue; };
+
+        match interaction {
+            Interaction::Hovered => select.highlighted = Some(row.index),
+            Interaction::Pressed => {
+                if select.is_disabled() {
+                    continue;
+                }
+
+                if select.select(row.index) {
+                    send_select_changed(entity, &select, &mut changed_events);
+                }
+
+                // Picking a disabled option keeps the popup open, like browsers do.
+                if select.open && !select.options[row.index].disabled {
+                    close_popup(&mut commands, &mut select);
+                }
+            },
+            Interaction::None => {},
+        }
+    }
+
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    for (entity, interaction, mut select) in &mut query_select {
+        if select.is_multiple() {
+            continue;
+        }
+
+        // The popup (and its rows) block the select, so a click on them leaves its Interaction at None.
+        let popup_pressed = select.popup.iter().chain(select.rows.iter().map(|(_, r)| r))
+            .any(|p| query_popup.get(*p).map(|i| *i == Interaction::Pressed).unwrap_or(false));
+
+        if *interaction == Interaction::Pressed && !popup_pressed {
+            if select.open {
+                close_popup(&mut commands, &mut select);
+            }
+            else {
+                open_popup(&mut commands, entity, &mut select);
+            }
+        }
+        // Clicking anywhere else closes it.
+        else if select.open && *interaction == Interaction::None && !popup_pressed {
+            close_popup(&mut commands, &mut select);
+        }
+    }
+}
+
+// Up/down moves through the options (skipping disabled ones), Enter/Space or A picks one, Escape or B closes.
+pub(crate) fn select_keyboard_input(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut changed_events: EventWriter<HydaSelectChanged>,
+    mut query_select: Query<(Entity, &mut HydaSelect), With<HydaFocused>>,
+) {
+    let keys: Vec<Key> = keyboard_events.read().filter(|e| e.state == ButtonState::Pressed).map(|e| e.logical_key.clone()).collect();
+
+    let mut gamepad_keys: Vec<Key> = Vec::new();
+
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadUp) { gamepad_keys.push(Key::ArrowUp); }
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadDown) { gamepad_keys.push(Key::ArrowDown); }
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::South) { gamepad_keys.push(Key::Enter); }
+    if gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::East) { gamepad_keys.push(Key::Escape); }
+
+    for (entity, mut select) in &mut query_select {
+        if select.is_disabled() {
+            continue;
+        }
+
+        let mut select_keys = keys.clone();
+
+        // While closed, the D-pad moves the focus instead of changing the selection.
+        for key in &gamepad_keys {
+            if select.open || select.is_multiple() || (*key != Key::ArrowUp && *key != Key::ArrowDown) {
+                select_keys.push(key.clone());
+            }
+        }
+
+        for key in &select_keys {
+            let is_list = select.open || select.is_multiple();
+
+            match key {
+                Key::ArrowUp | Key::ArrowDown => {
+                    let direction = if *key == Key::ArrowUp { -1 } else { 1 };
+
+                    let Some(next) = select.next_enabled(direction) else { continue; };
+
+                    // When closed, the arrows change the selection right away.
+                    if is_list {
+                        select.highlighted = Some(next);
+                    }
+                    else if select.select(next) {
+                        send_select_changed(entity, &select, &mut changed_events);
+                    }
+                },
+                Key::Enter | Key::Space => {
+                    if !is_list {
+                        open_popup(&mut commands, entity, &mut select);
+                        continue;
+                    }
+
+                    if let Some(h) = select.highlighted {
+                        if select.select(h) {
+                            send_select_changed(entity, &select, &mut changed_events);
+                        }
+                    }
+
+                    if select.open {
+                        close_popup(&mut commands, &mut select);
+                    }
+                },
+                Key::Escape => {
+                    if select.open {
+                        close_popup(&mut commands, &mut select);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+// Keeps the label and the row colors in sync with the selection.
+pub(crate) fn update_selects(
+    query_select: Query<&HydaSelect, Changed<HydaSelect>>,
+    mut query_text: Query<&mut Text>,
+    mut query_background: Query<&mut BackgroundColor, With<HydaSelectRow>>,
+) {
+    for select in &query_select {
+        if let Ok(mut text) = query_text.get_mut(select.label) {
+            let label = select.selected_label();
+
+            if text.sections[0].value != label {
+                text.sections[0].value = label;
+            }
+        }
+
+        for (index, row) in &select.rows {
+            let Ok(mut background) = query_background.get_mut(*row) else { continue; };
+
+            let color = if select.selected.contains(index) {
+                SELECTED_ROW_COLOR
+            }
+            else if select.highlighted == Some(*index) {
+                HIGHLIGHTED_ROW_COLOR
+            }
+            else {
+                Color::NONE
+            };
+
+            if background.0 != color {
+                background.0 = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,615 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemState;
+use ego_tree::NodeId;
+use scraper::{Html, Node as HtmlNode};
+use scraper::node::Text as HtmlText;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{HydaBindingItem, HydaBindings, HydaDocument, HydaElement, HydaHtml, parse_scraper_node, render_template, spawn_text_node};
+use crate::binding::get_item_path;
+use crate::dom::{HydaElementLocation, get_entity_map, get_location, get_parent_element, get_parent_style, new_html_element, refresh_styles};
+
+// On <template data-for="item in list"> elements. Its content is spawned once for every item of
+// the list (right after the template, which stays hidden) and kept in sync with HydaBindings:
+// items are matched by key, so only the ones added or removed get spawned or despawned.
+#[derive(Component, Debug, Clone)]
+pub struct HydaTemplate {
+    variable: String,
+    list: String,
+    instances: Vec<HydaTemplateInstance>,
+    // The nodes of removed instances, reused for new ones (ego_tree never frees nodes, so lists
+    // that change all the time would keep growing the document otherwise).
+    free: Vec<Vec<NodeId>>,
+}
+
+#[derive(Debug, Clone)]
+struct HydaTemplateInstance {
+    key: String,
+    node_ids: Vec<NodeId>,
+    entities: Vec<Entity>,
+}
+
+impl HydaTemplate {
+    // From the value of "data-for", like "item in inventory".
+    pub fn parse(data_for: &str) -> Option<Self> {
+        let (variable, list) = data_for.split_once(" in ")?;
+
+        if variable.trim().is_empty() || list.trim().is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            variable: variable.trim().to_string(),
+            list: list.trim().to_string(),
+            instances: Vec::new(),
+            free: Vec::new(),
+        })
+    }
+
+    pub fn variable(&self) -> &str {
+        return &self.variable;
+    }
+
+    pub fn list(&self) -> &str {
+        return &self.list;
+    }
+
+    // The keys of the items that are currently spawned, in order.
+    pub fn keys(&self) -> Vec<&str> {
+        return self.instances.iter().map(|i| i.key.as_str()).collect();
+    }
+
+    // "item.name" becomes "inventory[key].name", anything else stays the same.
+    fn scope_key(&self, name: &str, key: &str) -> String {
+        if name == self.variable {
+            return get_item_path(&self.list, key, "");
+        }
+
+        if let Some(field) = name.strip_prefix(self.variable.as_str()).and_then(|f| f.strip_prefix('.')) {
+            return get_item_path(&self.list, key, field);
+        }
+
+        return name.to_string();
+    }
+
+    // "{{item.name}}" becomes "{{inventory[key].name}}", so the text follows that item from now on.
+    fn scope_placeholders(&self, text: &str, key: &str) -> String {
+
+        let mut result = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else { break; };
+
+            result.push_str(&rest[..start]);
+            result.push_str("{{");
+            result.push_str(&self.scope_key(rest[start + 2..start + end].trim(), key));
+            result.push_str("}}");
+
+            rest = &rest[start + end + 2..];
+        }
+
+        result.push_str(rest);
+
+        return result;
+    }
+
+    // Attributes aren't bound, they get the values the item has when it's spawned.
+    fn scope_node(&self, node: &HtmlNode, key: &str, bindings: &HydaBindings) -> HtmlNode {
+        match node {
+            HtmlNode::Text(t) => HtmlNode::Text(HtmlText { text: self.scope_placeholders(&t.text, key).into() }),
+            HtmlNode::Element(e) => {
+                let attributes: HashMap<String, String> = e.attrs()
+                    .map(|(k, v)| match k {
+                        // Conditions use binding names directly, "data-if="item.equipped"" (maybe with a "!").
+                        "data-if" | "data-show" => {
+                            let (negation, name) = if v.trim().starts_with('!') { ("!", &v.trim()[1..]) } else { ("", v.trim()) };
+                            (k.to_string(), format!("{}{}", negation, self.scope_key(name.trim(), key)))
+                        },
+                        _ => (k.to_string(), render_template(&self.scope_placeholders(v, key), bindings)),
+                    })
+                    .collect();
+
+                HtmlNode::Element(new_html_element(e.name.clone(), &attributes))
+            },
+            _ => node.clone(),
+        }
+    }
+
+    fn copy_children(&self, html: &mut Html, from: NodeId, to: NodeId, key: &str, bindings: &HydaBindings) {
+
+        let children: Vec<NodeId> = html.tree.get(from).map(|n| n.children().map(|c| c.id()).collect()).unwrap_or_default();
+
+        for c in children {
+            let value = self.scope_node(html.tree.get(c).unwrap().value(), key, bindings);
+            let new_id = html.tree.get_mut(to).unwrap().append(value).id();
+
+            self.copy_children(html, c, new_id, key, bindings);
+        }
+    }
+
+    // The nodes of the template that get copied, whitespace between them doesn't count.
+    fn get_content(html: &Html, template_id: NodeId) -> Vec<NodeId> {
+
+        let Some(template) = html.tree.get(template_id) else { return Vec::new(); };
+
+        // The parser keeps the content of a <template> in a fragment inside of it.
+        let content = template.children().find(|c| c.value().is_fragment()).unwrap_or(template);
+
+        return content.children()
+            .filter(|c| !matches!(c.value(), HtmlNode::Text(t) if t.trim().is_empty()))
+            .map(|c| c.id())
+            .collect();
+    }
+
+    // A copy of the content for "key", not attached to the document yet.
+    fn instantiate(&self, html: &mut Html, template_id: NodeId, key: &str, bindings: &HydaBindings) -> Vec<NodeId> {
+
+        let mut roots: Vec<NodeId> = Vec::new();
+
+        for c in Self::get_content(html, template_id) {
+            let value = self.scope_node(html.tree.get(c).unwrap().value(), key, bindings);
+            let new_id = html.tree.orphan(value).id();
+
+            self.copy_children(html, c, new_id, key, bindings);
+
+            roots.push(new_id);
+        }
+
+        return roots;
+    }
+
+    fn overwrite(&self, html: &mut Html, from: NodeId, to: NodeId, key: &str, bindings: &HydaBindings) {
+
+        let value = self.scope_node(html.tree.get(from).unwrap().value(), key, bindings);
+        *html.tree.get_mut(to).unwrap().value() = value;
+
+        let pairs: Vec<(NodeId, NodeId)> = html.tree.get(from).unwrap().children().map(|c| c.id())
+            .zip(html.tree.get(to).unwrap().children().map(|c| c.id()))
+            .collect();
+
+        for (f, t) in pairs {
+            self.overwrite(html, f, t, key, bindings);
+        }
+    }
+
+    // Same as instantiate(), but writing the content for "key" over the nodes of a removed instance.
+    // Returns false (without touching them) if they don't have the same shape as the content anymore.
+    fn reuse(&self, html: &mut Html, template_id: NodeId, node_ids: &Vec<NodeId>, key: &str, bindings: &HydaBindings) -> bool {
+
+        let content = Self::get_content(html, template_id);
+
+        if content.len() != node_ids.len() || content.iter().zip(node_ids).any(|(c, n)| !has_same_shape(html, *c, *n)) {
+            return false;
+        }
+
+        for (c, n) in content.iter().zip(node_ids) {
+            self.overwrite(html, *c, *n, key, bindings);
+        }
+
+        return true;
+    }
+}
+
+fn has_same_shape(html: &Html, a: NodeId, b: NodeId) -> bool {
+
+    let (Some(a), Some(b)) = (html.tree.get(a), html.tree.get(b)) else { return false; };
+
+    let a_children: Vec<NodeId> = a.children().map(|c| c.id()).collect();
+    let b_children: Vec<NodeId> = b.children().map(|c| c.id()).collect();
+
+    return a_children.len() == b_children.len() && a_children.iter().zip(&b_children).all(|(x, y)| has_same_shape(html, *x, *y));
+}
+
+fn get_item_keys(items: &Vec<HydaBindingItem>, list: &str) -> Vec<String> {
+
+    let mut keys: Vec<String> = Vec::new();
+
+    for item in items {
+        if keys.contains(&item.key) {
+            warn!("bevy_hyda: the key \"{}\" is used by more than one item of \"{}\"", item.key, list);
+            continue;
+        }
+
+        keys.push(item.key.clone());
+    }
+
+    return keys;
+}
+
+// For every key, the index of the current instance that has it (None for the new ones),
+// and the indices of the current instances whose key isn't there anymore.
+fn match_instances(current: &Vec<&str>, keys: &Vec<String>) -> (Vec<Option<usize>>, Vec<usize>) {
+
+    let matched: Vec<Option<usize>> = keys.iter().map(|k| current.iter().position(|c| c == k)).collect();
+    let removed: Vec<usize> = (0..current.len()).filter(|i| !keys.iter().any(|k| k == current[*i])).collect();
+
+    return (matched, removed);
+}
+
+fn update_template(world: &mut World, entity: Entity, bindings: &HydaBindings) {
+
+    let Some(location) = get_location(world, entity) else { return; };
+    let Some(mut template) = world.get::<HydaTemplate>(entity).cloned() else { return; };
+
+    let keys = get_item_keys(bindings.list(&template.list).unwrap_or(&Vec::new()), &template.list);
+
+    // Instances without entities are the ones of a respawned template (see restore_template_instances()).
+    if template.keys() == keys && template.instances.iter().all(|i| !i.entities.is_empty()) {
+        return;
+    }
+
+    let (matched, removed_indices) = match_instances(&template.keys(), &keys);
+
+    let mut current: Vec<Option<HydaTemplateInstance>> = template.instances.drain(..).map(Some).collect();
+
+    let removed: Vec<HydaTemplateInstance> = removed_indices.iter().map(|i| current[*i].take().unwrap()).collect();
+
+    let mut instances: Vec<HydaTemplateInstance> = Vec::new();
+    let mut added: Vec<usize> = Vec::new();
+    let mut removed_node_ids: HashSet<NodeId> = HashSet::new();
+
+    {
+        let mut html = location.html.lock().unwrap();
+
+        for i in &removed {
+            for n in &i.node_ids {
+                if let Some(node) = html.tree.get(*n) {
+                    removed_node_ids.extend(node.descendants().map(|d| d.id()));
+                }
+
+                if let Some(mut node) = html.tree.get_mut(*n) {
+                    node.detach();
+                }
+            }
+
+            template.free.push(i.node_ids.clone());
+        }
+
+        for (k, m) in keys.iter().zip(matched) {
+            if let Some(i) = m {
+                let instance = current[i].take().unwrap();
+
+                if instance.entities.is_empty() {
+                    added.push(instances.len());
+                }
+
+                instances.push(instance);
+                continue;
+            }
+
+            let mut node_ids: Option<Vec<NodeId>> = None;
+
+            while let Some(f) = template.free.pop() {
+                if template.reuse(&mut html, location.node_id, &f, k, bindings) {
+                    node_ids = Some(f);
+                    break;
+                }
+            }
+
+            added.push(instances.len());
+            instances.push(HydaTemplateInstance {
+                key: k.clone(),
+                node_ids: node_ids.unwrap_or_else(|| template.instantiate(&mut html, location.node_id, k, bindings)),
+                entities: Vec::new(),
+            });
+        }
+
+        // Everything goes right after the template, in the order of the list.
+        let mut previous = location.node_id;
+
+        for i in &instances {
+            for n in &i.node_ids {
+                html.tree.get_mut(previous).unwrap().insert_id_after(*n);
+                previous = *n;
+            }
+        }
+    }
+
+    for i in &removed {
+        for e in &i.entities {
+            if world.get_entity(*e).is_some() {
+                world.entity_mut(*e).despawn_recursive();
+            }
+        }
+    }
+
+    // The removed nodes aren't in the document anymore, so they can't match any rule (the reused ones match again below).
+    if let Some(mut document) = world.get_mut::<HydaDocument>(location.document) {
+        for s in &mut document.styles {
+            s.node_ids.retain(|n| !removed_node_ids.contains(n));
+        }
+    }
+
+    refresh_styles(world, &location);
+
+    let parent = world.get::<Parent>(entity).map(|p| p.get());
+    let parent_element = get_parent_element(world, &location).unwrap_or(Entity::PLACEHOLDER);
+    let parent_style = get_parent_style(world, &location);
+    let styles = world.get::<HydaDocument>(location.document).unwrap().styles.clone();
+
+    let mut system_state: SystemState<(Commands, Res<AssetServer>)> = SystemState::new(world);
+
+    {
+        let (mut commands, asset_server) = system_state.get_mut(world);
+        let html = location.html.lock().unwrap();
+
+        for a in &added {
+            let instance = &mut instances[*a];
+
+            for n in &instance.node_ids {
+                let ast = parse_scraper_node(html.tree.get(*n).unwrap(), &styles, &parent_style, &location.html);
+
+                let mut text_sections: Vec<(TextSection, Entity)> = Vec::new();
+
+                let (e, _) = ast.spawn_ui_impl(&mut commands, &asset_server, parent_element, &parent_style, &mut text_sections);
+
+                instance.entities.push(e);
+
+                // Text (or inline elements) at the top of the template get a text node of their own.
+                if !text_sections.is_empty() {
+                    instance.entities.push(spawn_text_node(&mut commands, parent_element, &mut text_sections));
+                }
+            }
+        }
+    }
+
+    system_state.apply(world);
+
+    // The entities for plain text don't have anything, the text is in the text node.
+    for a in &added {
+        instances[*a].entities.retain(|e| {
+            let is_used = world.get::<Node>(*e).is_some() || world.get::<HydaElement>(*e).is_some();
+
+            if !is_used {
+                world.entity_mut(*e).despawn();
+            }
+
+            is_used
+        });
+    }
+
+    if let Some(p) = parent {
+        let ordered: Vec<Entity> = instances.iter().flat_map(|i| i.entities.iter().copied()).filter(|e| world.get::<Node>(*e).is_some()).collect();
+
+        world.entity_mut(p).remove_children(&ordered);
+
+        let index = world.get::<Children>(p).and_then(|c| c.iter().position(|e| *e == entity)).unwrap_or(0);
+
+        world.entity_mut(p).insert_children(index + 1, &ordered);
+    }
+
+    if let Some(mut t) = world.get_mut::<HydaTemplate>(entity) {
+        t.instances = instances;
+        t.free = template.free;
+    }
+}
+
+// The templates inside of "location" (or that are "location"), with their instances taken out of the
+// document while it gets parsed again: they're spawned by their template, not as part of the document.
+pub(crate) fn detach_template_instances(world: &mut World, location: &HydaElementLocation) -> Vec<(NodeId, HydaTemplate)> {
+
+    let mut query_templates = world.query::<(&HydaElement, &HydaTemplate)>();
+
+    let templates: Vec<(NodeId, HydaTemplate)> = query_templates.iter(world)
+        .filter_map(|(element, template)| element.source().map(|s| (s, template)))
+        .filter(|(source, _)| Arc::ptr_eq(source.html(), &location.html))
+        .map(|(source, template)| (source.node_id(), template.clone()))
+        .collect();
+
+    let mut html = location.html.lock().unwrap();
+
+    let inside: Vec<(NodeId, HydaTemplate)> = templates.into_iter()
+        .filter(|(n, _)| *n == location.node_id || html.tree.get(*n).map_or(false, |node| node.ancestors().any(|a| a.id() == location.node_id)))
+        .collect();
+
+    for (_, t) in &inside {
+        for n in t.instances.iter().flat_map(|i| i.node_ids.iter()) {
+            if let Some(mut node) = html.tree.get_mut(*n) {
+                node.detach();
+            }
+        }
+    }
+
+    return inside;
+}
+
+// Puts the instances back after their template, which gets the state it had before being respawned.
+pub(crate) fn restore_template_instances(world: &mut World, html: &HydaHtml, templates: Vec<(NodeId, HydaTemplate)>) {
+
+    let entities = get_entity_map(world, html);
+
+    for (node_id, mut template) in templates {
+        {
+            let mut html = html.lock().unwrap();
+            let mut previous = node_id;
+
+            for n in template.instances.iter().flat_map(|i| i.node_ids.iter()) {
+                html.tree.get_mut(previous).unwrap().insert_id_after(*n);
+                previous = *n;
+            }
+        }
+
+        // The old entities were despawned along with the template, update_template() spawns them again.
+        for i in &mut template.instances {
+            i.entities.clear();
+        }
+
+        if let Some(e) = entities.get(&node_id) {
+            world.entity_mut(*e).insert(template);
+        }
+    }
+}
+
+// When an element spawned for an instance gets respawned, so its template keeps track of the new one.
+pub(crate) fn replace_instance_entity(world: &mut World, old: Entity, new: Entity) {
+
+    let mut query_templates = world.query::<&mut HydaTemplate>();
+
+    for mut t in query_templates.iter_mut(world) {
+        for e in t.instances.iter_mut().flat_map(|i| i.entities.iter_mut()).filter(|e| **e == old) {
+            *e = new;
+        }
+    }
+}
+
+pub(crate) fn update_templates(world: &mut World) {
+
+    let bindings_changed = world.resource_ref::<HydaBindings>().is_changed();
+
+    let mut query_templates = world.query::<(Entity, Ref<HydaTemplate>)>();
+
+    let templates: Vec<Entity> = query_templates.iter(world).filter(|(_, t)| bindings_changed || t.is_added()).map(|(e, _)| e).collect();
+
+    if templates.is_empty() {
+        return;
+    }
+
+    let bindings = world.resource::<HydaBindings>().clone();
+
+    for t in templates {
+        update_template(world, t, &bindings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HydaDomCommandsExt;
+    use crate::tests::{test_app, spawn_html};
+
+    fn count_items(world: &mut World) -> usize {
+        let mut query_elements = world.query::<&HydaElement>();
+        return query_elements.iter(world).filter(|e| e.tag == "li").count();
+    }
+
+    fn keys(k: &[&str]) -> Vec<String> {
+        return k.iter().map(|k| k.to_string()).collect();
+    }
+
+    #[test]
+    fn parse_data_for() {
+        let template = HydaTemplate::parse(" item  in inventory ").unwrap();
+
+        assert_eq!(template.variable(), "item");
+        assert_eq!(template.list(), "inventory");
+        assert!(template.keys().is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_data_for() {
+        assert!(HydaTemplate::parse("").is_none());
+        assert!(HydaTemplate::parse("item").is_none());
+        assert!(HydaTemplate::parse("item of inventory").is_none());
+        assert!(HydaTemplate::parse(" in inventory").is_none());
+        assert!(HydaTemplate::parse("item in  ").is_none());
+    }
+
+    #[test]
+    fn duplicate_keys_are_skipped() {
+        let items = vec![HydaBindingItem::new("a"), HydaBindingItem::new("b"), HydaBindingItem::new("a")];
+
+        assert_eq!(get_item_keys(&items, "list"), keys(&["a", "b"]));
+    }
+
+    #[test]
+    fn match_reordered_keys() {
+        let (matched, removed) = match_instances(&vec!["a", "b", "c"], &keys(&["c", "a", "b"]));
+
+        assert_eq!(matched, vec![Some(2), Some(0), Some(1)]);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn match_added_keys() {
+        let (matched, removed) = match_instances(&vec!["a", "c"], &keys(&["a", "b", "c", "d"]));
+
+        assert_eq!(matched, vec![Some(0), None, Some(1), None]);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn match_removed_keys() {
+        let (matched, removed) = match_instances(&vec!["a", "b", "c"], &keys(&["c"]));
+
+        assert_eq!(matched, vec![Some(2)]);
+        assert_eq!(removed, vec![0, 1]);
+    }
+
+    #[test]
+    fn scope_placeholders_of_the_item() {
+        let template = HydaTemplate::parse("item in inventory").unwrap();
+
+        assert_eq!(
+            template.scope_placeholders("{{item.name}} x{{ item.count }} of {{total}}, {{item}} {{items.name}}", "sword"),
+            "{{inventory[sword].name}} x{{inventory[sword].count}} of {{total}}, {{inventory[sword]}} {{items.name}}",
+        );
+
+        assert_eq!(template.scope_placeholders("{{item.name", "sword"), "{{item.name");
+    }
+
+    #[test]
+    fn reuse_nodes_of_removed_instances() {
+        let template = HydaTemplate::parse("item in inventory").unwrap();
+
+        let mut html = Html::parse_fragment("<template><li class=\"{{item.kind}}\">{{item.name}}</li></template>");
+        let template_id = html.tree.root().descendants().find(|n| matches!(n.value(), HtmlNode::Element(e) if &*e.name.local == "template")).unwrap().id();
+
+        let mut bindings = HydaBindings::default();
+        bindings.set_list("inventory", vec![
+            HydaBindingItem::new("sword").with("kind", "weapon"),
+            HydaBindingItem::new("shield").with("kind", "armor"),
+        ]);
+
+        let node_ids = template.instantiate(&mut html, template_id, "sword", &bindings);
+
+        assert_eq!(node_ids.len(), 1);
+
+        assert!(template.reuse(&mut html, template_id, &node_ids, "shield", &bindings));
+
+        let li = html.tree.get(node_ids[0]).unwrap();
+
+        let HtmlNode::Element(e) = li.value() else { panic!("the <li> should be an element") };
+        assert_eq!(e.attr("class"), Some("armor"));
+
+        let HtmlNode::Text(t) = li.first_child().unwrap().value() else { panic!("the <li> should have its text") };
+        assert_eq!(&*t.text, "{{inventory[shield].name}}");
+
+        // Different shapes can't be reused.
+        html.tree.get_mut(node_ids[0]).unwrap().append(HtmlNode::Text(HtmlText { text: "!".into() }));
+
+        assert!(!template.reuse(&mut html, template_id, &node_ids, "sword", &bindings));
+    }
+
+    #[test]
+    fn respawned_templates_keep_their_instances() {
+        let mut app = test_app();
+        app.init_resource::<HydaBindings>();
+        app.add_systems(PostUpdate, update_templates);
+
+        app.world_mut().resource_mut::<HydaBindings>().set_list("inventory", vec![
+            HydaBindingItem::new("sword").with("name", "Sword"),
+            HydaBindingItem::new("shield").with("name", "Shield"),
+        ]);
+
+        spawn_html(&mut app, "<ul id=\"items\"><template data-for=\"item in inventory\"><li>{{item.name}}</li></template></ul>");
+        app.update();
+
+        assert_eq!(count_items(app.world_mut()), 2);
+
+        let world = app.world_mut();
+        let ul = world.query::<(Entity, &HydaElement)>().iter(world).find(|(_, e)| e.tag == "ul").unwrap().0;
+
+        app.world_mut().commands().append_html(ul, "<li>Potion</li>");
+        app.world_mut().flush();
+        app.update();
+
+        assert_eq!(count_items(app.world_mut()), 3);
+
+        let world = app.world_mut();
+        let html = world.query::<&HydaDocument>().single(world).html().clone();
+        // Plus the one in the content of the template.
+        assert_eq!(html.lock().unwrap().select(&scraper::Selector::parse("li").unwrap()).count(), 4);
+    }
+}